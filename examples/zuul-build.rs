@@ -1,12 +1,15 @@
 //! # zuul-build
 //!
 //! `zuul-build` is like 'tail -f' for builds result.
-use clap::{App, Arg};
-use env_logger;
-use futures_util::pin_mut;
-use futures_util::stream::StreamExt;
+use chrono::{DateTime, Utc};
+use clap::{value_t, App, Arg};
+use comfy_table::presets::ASCII_FULL;
+use comfy_table::Table;
+use futures_util::future::ready;
+use futures_util::stream::{Stream, StreamExt};
+use std::pin::Pin;
 use std::time::Duration;
-use zuul;
+use zuul::BuildResult;
 
 #[tokio::main]
 async fn main() {
@@ -18,40 +21,210 @@ async fn main() {
             Arg::with_name("url")
                 .long("url")
                 .takes_value(true)
-                .required(true)
-                .help("The zuul api"),
+                .help("The zuul api, default to the url of ~/.config/zuul/client.toml"),
+        )
+        .arg(
+            Arg::with_name("tenant")
+                .long("tenant")
+                .takes_value(true)
+                .help("The zuul tenant name, the url must be the api root, not a tenant api"),
+        )
+        .arg(
+            Arg::with_name("since-uuid")
+                .long("since-uuid")
+                .takes_value(true)
+                .help("Catchup until a certain build"),
         )
         .arg(
             Arg::with_name("since")
                 .long("since")
                 .takes_value(true)
-                .help("Catchup until a certain build"),
+                .conflicts_with("since-uuid")
+                .help("Deprecated, use --since-uuid"),
+        )
+        .arg(
+            Arg::with_name("since-time")
+                .long("since-time")
+                .takes_value(true)
+                .conflicts_with_all(&["since-uuid", "since"])
+                .help("Only show the builds that ended after this RFC 3339 time, then stop"),
+        )
+        .arg(
+            Arg::with_name("filter-result")
+                .long("filter-result")
+                .takes_value(true)
+                .validator(|v| {
+                    v.parse::<BuildResult>()
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                })
+                .help("Only show the builds with this result"),
+        )
+        .arg(
+            Arg::with_name("project")
+                .long("project")
+                .takes_value(true)
+                .help("Only show the builds of this project"),
+        )
+        .arg(
+            Arg::with_name("job")
+                .long("job")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only show the builds of this job, can be repeated to show any of the jobs"),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .takes_value(true)
+                .help("Stop after printing this many builds"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .possible_values(&["text", "json-lines", "table"])
+                .default_value("text")
+                .help("The output format, the table is printed when the stream ends"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Deprecated, use --output json-lines"),
         )
-        .arg(Arg::with_name("json").long("json").help("Output json"))
         .get_matches();
-    let client = zuul::create_client(matches.value_of("url").unwrap()).expect("Invalid url");
-    let since = matches.value_of("since").map(|s| String::from(s));
-    let json = matches.is_present("json");
+    let tenant = matches.value_of("tenant").map(String::from);
+    let (client, tenant, url) = match matches.value_of("url") {
+        Some(url) => (
+            zuul::create_client(url).expect("Invalid url"),
+            tenant,
+            url.to_string(),
+        ),
+        None => {
+            let config = zuul::ZuulConfig::from_default_path().expect("Missing --url");
+            let mut builder = zuul::ZuulClientBuilder::new(&config.url);
+            if let Some(token) = config.token {
+                builder = builder.with_auth_token(&token);
+            }
+            let client = builder.build().expect("Invalid config");
+            (client, tenant.or(config.tenant), config.url)
+        }
+    };
+    if tenant.is_some() && url.contains("/tenant/") {
+        eprintln!(
+            "The url {} is already scoped to a tenant, --tenant needs the api root url",
+            url
+        );
+        std::process::exit(1);
+    }
+    if matches.is_present("since") {
+        eprintln!("warning: --since is deprecated, use --since-uuid");
+    }
+    let since = matches
+        .value_of("since-uuid")
+        .or_else(|| matches.value_of("since"))
+        .map(String::from);
+    let since_time = matches.value_of("since-time").map(|time| {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap_or_else(|e| {
+                eprintln!("Invalid --since-time {}: {}", time, e);
+                std::process::exit(1);
+            })
+            .with_timezone(&Utc)
+    });
+    let output = match matches.is_present("json") {
+        true => {
+            eprintln!("warning: --json is deprecated, use --output json-lines");
+            "json-lines"
+        }
+        false => matches.value_of("output").unwrap(),
+    };
+    let limit = match matches.is_present("limit") {
+        true => Some(value_t!(matches, "limit", usize).unwrap_or_else(|e| e.exit())),
+        false => None,
+    };
+    let filter_result = match matches.is_present("filter-result") {
+        true => Some(value_t!(matches, "filter-result", BuildResult).unwrap_or_else(|e| e.exit())),
+        false => None,
+    };
+    let project = matches.value_of("project").map(String::from);
+    let jobs: Vec<String> = matches
+        .values_of("job")
+        .map(|jobs| jobs.map(String::from).collect())
+        .unwrap_or_default();
+
+    let predicate = move |build: &zuul::Build| {
+        filter_result
+            .as_ref()
+            .is_none_or(|result| &build.result == result)
+            && project
+                .as_ref()
+                .is_none_or(|project| &build.project == project)
+            && (jobs.is_empty() || jobs.contains(&build.job_name))
+    };
 
     // Start the build stream
-    let s = client.builds_tail(Duration::from_secs(10), since);
-    pin_mut!(s);
+    let tenant_client = tenant.map(|tenant| client.tenant(&tenant));
+    let delay = Duration::from_secs(10);
+    let mut s: Pin<Box<dyn Stream<Item = _>>> = match (&tenant_client, since_time) {
+        (Some(tenant_client), Some(since_time)) => Box::pin(
+            tenant_client
+                .builds_stream_since_time(since_time)
+                .filter(move |build| ready(predicate(build)))
+                .map(Ok),
+        ),
+        (None, Some(since_time)) => Box::pin(
+            client
+                .builds_stream_since_time(since_time)
+                .filter(move |build| ready(predicate(build)))
+                .map(Ok),
+        ),
+        (Some(tenant_client), None) => {
+            Box::pin(tenant_client.builds_tail_where(delay, since, predicate))
+        }
+        (None, None) => Box::pin(client.builds_tail_where(delay, since, predicate)),
+    };
 
-    // Print new builds
-    while let Some(build) = s.next().await {
-        if json {
-            match serde_json::to_string(&build) {
+    // Print new builds, until the stream ends or the user interrupts
+    let mut table = Table::new();
+    table
+        .load_preset(ASCII_FULL)
+        .set_header(["UUID", "Project", "Job", "Result", "Duration", "End Time"]);
+    let mut count = 0;
+    while limit.is_none_or(|limit| count < limit) {
+        let build = tokio::select! {
+            build = s.next() => build,
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        let build = match build {
+            Some(Ok(build)) => build,
+            Some(Err(e)) => {
+                eprintln!("Failed to get builds: {}", e);
+                std::process::exit(1);
+            }
+            None => break,
+        };
+        match output {
+            "json-lines" => match serde_json::to_string(&build) {
                 Ok(v) => println!("{}", v),
                 Err(v) => println!("{:?}", v),
+            },
+            "table" => {
+                table.add_row([
+                    build.uuid.clone(),
+                    build.project.clone(),
+                    build.job_name.clone(),
+                    build.result.to_string(),
+                    format!("{}s", build.duration_secs),
+                    build.end_time.to_rfc3339(),
+                ]);
             }
-        } else {
-            println!(
-                "{} {} {} {}",
-                build.log_url.unwrap_or("N/A".to_string()),
-                build.uuid,
-                build.project,
-                build.job_name
-            )
+            _ => println!("{}", build),
         }
+        count += 1;
+    }
+    if output == "table" {
+        println!("{}", table);
     }
 }
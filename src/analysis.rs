@@ -0,0 +1,99 @@
+//! Helpers to analyze a set of builds, without querying the api.
+
+use crate::{Build, BuildResult};
+use std::collections::HashMap;
+
+/// Group the builds by result.
+pub fn group_builds_by_result(builds: &[Build]) -> HashMap<BuildResult, Vec<&Build>> {
+    builds.iter().fold(HashMap::new(), |mut acc, build| {
+        acc.entry(build.result.clone()).or_default().push(build);
+        acc
+    })
+}
+
+/// Count the builds per result.
+pub fn result_counts(builds: &[Build]) -> HashMap<BuildResult, usize> {
+    builds.iter().fold(HashMap::new(), |mut acc, build| {
+        *acc.entry(build.result.clone()).or_default() += 1;
+        acc
+    })
+}
+
+/// Group the builds by job name, keeping the input order in each group.
+pub fn group_builds_by_job(builds: &[Build]) -> HashMap<&str, Vec<&Build>> {
+    builds.iter().fold(HashMap::new(), |mut acc, build| {
+        acc.entry(build.job_name.as_str()).or_default().push(build);
+        acc
+    })
+}
+
+/// Select the builds whose duration is within the inclusive range, empty when `min_secs > max_secs`.
+pub fn filter_builds_by_duration(builds: &[Build], min_secs: u32, max_secs: u32) -> Vec<&Build> {
+    builds
+        .iter()
+        .filter(|build| (min_secs..=max_secs).contains(&build.duration_secs))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{drop_milli, make_build};
+    use chrono::Utc;
+
+    #[test]
+    fn it_groups_builds_by_result() {
+        let now = drop_milli(Utc::now());
+        let mut builds = vec![
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        ];
+        builds[1].result = BuildResult::Failure;
+
+        let groups = group_builds_by_result(&builds);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&BuildResult::Success], vec![&builds[0], &builds[2]]);
+        assert_eq!(groups[&BuildResult::Failure], vec![&builds[1]]);
+
+        let counts = result_counts(&builds);
+        assert_eq!(counts[&BuildResult::Success], 2);
+        assert_eq!(counts[&BuildResult::Failure], 1);
+        assert!(result_counts(&[]).is_empty());
+    }
+
+    #[test]
+    fn it_groups_builds_by_job() {
+        let now = drop_milli(Utc::now());
+        let mut builds = vec![
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        ];
+        builds[1].job_name = "linters".to_string();
+
+        let groups = group_builds_by_job(&builds);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["job"], vec![&builds[0], &builds[2]]);
+        assert_eq!(groups["linters"], vec![&builds[1]]);
+    }
+
+    #[test]
+    fn it_filters_builds_by_duration() {
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (1..=5)
+            .map(|idx| {
+                let mut build = make_build(&idx.to_string(), now);
+                build.duration_secs = idx * 10;
+                build
+            })
+            .collect();
+
+        assert_eq!(
+            filter_builds_by_duration(&builds, 20, 40),
+            vec![&builds[1], &builds[2], &builds[3]]
+        );
+        assert_eq!(filter_builds_by_duration(&builds, 50, 50), vec![&builds[4]]);
+        assert!(filter_builds_by_duration(&builds, 40, 20).is_empty());
+    }
+}
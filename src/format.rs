@@ -0,0 +1,114 @@
+//! Helpers to render builds.
+
+use crate::Build;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// The csv columns, the artifacts are reported as a count.
+const CSV_HEADER: [&str; 19] = [
+    "uuid",
+    "job_name",
+    "result",
+    "start_time",
+    "end_time",
+    "duration",
+    "voting",
+    "log_url",
+    "artifacts",
+    "project",
+    "branch",
+    "pipeline",
+    "change",
+    "patchset",
+    "ref",
+    "ref_url",
+    "event_id",
+    "event_timestamp",
+    "node_name",
+];
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn csv_record(build: &Build) -> [String; 19] {
+    [
+        build.uuid.clone(),
+        build.job_name.clone(),
+        build.result.to_string(),
+        format_time(&build.start_time),
+        format_time(&build.end_time),
+        build.duration_secs.to_string(),
+        build.voting.to_string(),
+        build.log_url.clone().unwrap_or_default(),
+        build.artifacts.len().to_string(),
+        build.project.clone(),
+        build.branch.clone(),
+        build.pipeline.clone(),
+        build.change.map(|c| c.to_string()).unwrap_or_default(),
+        build.patchset.clone().unwrap_or_default(),
+        build.change_ref.clone(),
+        build.ref_url.clone().unwrap_or_default(),
+        build.event_id.clone(),
+        build
+            .event_timestamp
+            .as_ref()
+            .map(format_time)
+            .unwrap_or_default(),
+        build.node_name.clone().unwrap_or_default(),
+    ]
+}
+
+/// Write the records as RFC 4180 csv.
+fn write_csv<I, R>(records: I) -> String
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(Vec::new());
+    for record in records {
+        writer
+            .write_record(record)
+            .expect("Writing to a Vec can't fail");
+    }
+    let data = writer.into_inner().expect("Writing to a Vec can't fail");
+    String::from_utf8(data).expect("The records are valid utf-8")
+}
+
+/// Render a build as a csv row, see [builds_to_csv] for the columns.
+pub fn build_to_csv_row(build: &Build) -> String {
+    write_csv([csv_record(build)])
+}
+
+/// Render builds as csv, with a header row.
+pub fn builds_to_csv(builds: &[Build]) -> String {
+    let header = CSV_HEADER.map(String::from);
+    write_csv(std::iter::once(header).chain(builds.iter().map(csv_record)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{drop_milli, make_build};
+    use chrono::TimeZone;
+
+    #[test]
+    fn it_formats_builds_to_csv() {
+        let end_time = Utc.with_ymd_and_hms(2021, 5, 4, 12, 0, 0).unwrap();
+        let mut build = make_build("build1", drop_milli(end_time));
+        build.job_name = "tox, \"py38\"".to_string();
+
+        let row = build_to_csv_row(&build);
+        assert!(row.starts_with("build1,\"tox, \"\"py38\"\"\",SUCCESS,2021-05-04T11:18:00Z,"));
+        assert!(row.ends_with(",uuid,2021-05-04T11:10:00Z,centos-8\r\n"));
+
+        let csv = builds_to_csv(&[build.clone(), build]);
+        let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert_eq!(format!("{}\r\n", lines[1]), row);
+        assert_eq!(builds_to_csv(&[]), format!("{}\r\n", CSV_HEADER.join(",")));
+    }
+}
@@ -17,7 +17,7 @@
 //! ```rust, no_run
 //! # extern crate tokio;
 //! #[tokio::main]
-//! async fn main() -> Result<(), reqwest::Error> {
+//! async fn main() -> Result<(), zuul::ZuulError> {
 //!     // Create the client
 //!     let client = zuul::create_client("https://zuul.example.org/api/tenant/name")
 //!             .expect("Invalid url");
@@ -29,6 +29,13 @@
 //! }
 //! ```
 //!
+//! # Features
+//!
+//! - `cache`: the `ZuulClientBuilder::with_cache` response cache.
+//! - `proptest`: the `Build::arbitrary` strategy, for property based tests.
+//! - `testing`: the `zuul::testing` module with a mocked api, for the tests of the crates using the client.
+//! - `tracing`: the [tracing](https://docs.rs/tracing) spans of the api calls, with the url, tenant and result count.
+//!
 //! Checkout the [zuul-build.rs](https://github.com/TristanCacqueray/zuul-rs/blob/main/examples/zuul-build.rs)
 //! example for a complete async-stream usage.
 use async_stream::stream;
@@ -36,391 +43,6379 @@ use chrono::{DateTime, Utc};
 use futures_core::stream::Stream;
 use futures_util::StreamExt;
 use log::{debug, error};
-use reqwest;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json;
-use std::collections::HashSet;
-use std::thread;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio_retry::strategy::{jitter, ExponentialBackoff};
 use tokio_retry::Retry;
+use tokio_util::sync::CancellationToken;
 use url::{ParseError, Url};
 
+pub mod analysis;
+pub mod format;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 /// The client.
+#[derive(Clone)]
 pub struct Zuul {
     client: reqwest::Client,
     api: Url,
+    retry: RetryStrategy,
+    authenticated: bool,
+    metrics: Arc<ZuulMetrics>,
+    rate_limiter: Option<Arc<governor::DefaultDirectRateLimiter>>,
+    max_page_size: Arc<tokio::sync::OnceCell<Option<u32>>>,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<ResponseCache>>,
+}
+
+/// The client error.
+#[derive(Debug)]
+pub enum ZuulError {
+    /// The http request failed.
+    Http(reqwest::Error),
+    /// The object could not be decoded.
+    Decode {
+        /// The object uuid, if available.
+        uuid: String,
+        /// The decoding error.
+        source: serde_json::Error,
+    },
+    /// The requested object does not exist.
+    NotFound,
+    /// The request is not authorized.
+    Unauthorized,
+    /// The server failed with the given status code.
+    ServerError(u16),
+    /// The url is not valid.
+    InvalidUrl(ParseError),
+    /// The client configuration is not valid.
+    InvalidConfig(String),
+    /// The operation did not complete in time.
+    Timeout,
+    /// The server sent an invalid event.
+    InvalidEvent(String),
+}
+
+impl fmt::Display for ZuulError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZuulError::Http(e) => write!(f, "http error: {}", e),
+            ZuulError::Decode { uuid, source } => {
+                write!(f, "failed to decode {}: {}", uuid, source)
+            }
+            ZuulError::NotFound => write!(f, "not found"),
+            ZuulError::Unauthorized => write!(f, "unauthorized"),
+            ZuulError::ServerError(code) => write!(f, "server error: {}", code),
+            ZuulError::InvalidUrl(e) => write!(f, "invalid url: {}", e),
+            ZuulError::InvalidConfig(e) => write!(f, "invalid config: {}", e),
+            ZuulError::Timeout => write!(f, "timed out"),
+            ZuulError::InvalidEvent(e) => write!(f, "invalid event: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ZuulError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ZuulError::Http(e) => Some(e),
+            ZuulError::Decode { source, .. } => Some(source),
+            ZuulError::InvalidUrl(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ZuulError {
+    fn from(e: reqwest::Error) -> Self {
+        ZuulError::Http(e)
+    }
+}
+
+impl From<ParseError> for ZuulError {
+    fn from(e: ParseError) -> Self {
+        ZuulError::InvalidUrl(e)
+    }
+}
+
+/// Record the number of results in the current span, when the tracing feature is enabled.
+fn record_count<T>(items: Vec<T>) -> Vec<T> {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("result_count", items.len());
+    items
 }
 
 /// Parse the api root url, ensuring it is slash terminated to enable Path::join.
 fn parse_root_url(url: &str) -> Result<Url, ParseError> {
     let mut url = Url::parse(url)?;
-    if url.path().chars().last().unwrap() != '/' {
+    if !url.path().ends_with('/') {
         let new_path = format!("{}/", String::from(url.path()));
         url.set_path(&new_path);
     }
     Ok(url)
 }
 
-/// Helper function to validate the api url and creates a client.
-pub fn create_client(api: &str) -> Result<Zuul, ParseError> {
-    let url = parse_root_url(api)?;
-    Ok(Zuul::new(url))
+/// The characters encoded in an url path segment, everything but the unreserved ones.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Percent-encode a project name, e.g. `org/repo`, for use in an url path.
+fn encode_project_name(name: &str) -> String {
+    utf8_percent_encode(name, PATH_SEGMENT).to_string()
 }
 
-impl Zuul {
-    /// Create a new client
-    pub fn new(api: Url) -> Self {
-        Zuul {
-            client: reqwest::Client::new(),
-            api,
+/// The retry strategy used to recover from transient api failures.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RetryStrategy {
+    /// The exponential backoff base delay in milli second.
+    pub initial_delay_ms: u64,
+    /// The maximum delay between attempts in second.
+    pub max_delay_secs: u64,
+    /// The maximum number of retries.
+    pub max_attempts: usize,
+}
+
+impl Default for RetryStrategy {
+    fn default() -> Self {
+        RetryStrategy {
+            initial_delay_ms: 10,
+            max_delay_secs: 13,
+            max_attempts: 10,
         }
     }
+}
 
-    /// Produce a continuous stream of unique build.
-    pub fn builds_tail(
-        &self,
-        loop_delay: Duration,
-        since: Option<String>,
-    ) -> impl Stream<Item = Build> + '_ {
-        let mut since = since.clone();
-        stream! {
-            loop {
-                match since.clone() {
-                    Some(uuid) => {
-                        for await (idx, build) in self.builds_stream().enumerate() {
-                            if (idx == 0) {
-                                since = Some(build.uuid.clone());
-                            }
-                            match &build.uuid[..] == uuid {
-                                true => break,
-                                false => yield build
-                            }
-                        }
-                    },
-                    None => {
-                        // get latest build
-                        let mut builds = self.builds(0, 1).await.unwrap();
-                        if let Some(Ok(build)) = builds.pop() {
-                            debug!("Current latest build is {:?}", build);
-                            since = Some(build.uuid.clone());
-                        }
-                        if let None = since {
-                            panic!("Could not get the latest build");
-                        }
-                    }
-                }
-                debug!("Now sleeping {:?}", loop_delay);
-                thread::sleep(loop_delay);
-            }
+impl RetryStrategy {
+    /// The delays between attempts.
+    fn delays(&self) -> impl Iterator<Item = Duration> {
+        ExponentialBackoff::from_millis(self.initial_delay_ms)
+            .max_delay(Duration::from_secs(self.max_delay_secs))
+            .map(jitter)
+            .take(self.max_attempts)
+    }
+}
+
+/// The recovery of a builds tail when a page can't be queried.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ErrorStrategy {
+    /// Back off and retry the failed page, the stream ends when the attempts are exhausted.
+    Retry(RetryStrategy),
+    /// Skip the failed page and continue with the next one.
+    Skip,
+    /// End the stream with the error.
+    Terminate,
+}
+
+impl Default for ErrorStrategy {
+    fn default() -> Self {
+        ErrorStrategy::Retry(RetryStrategy::default())
+    }
+}
+
+/// The client counters, shared by the clones of a client.
+#[derive(Debug, Default)]
+pub struct ZuulMetrics {
+    /// The number of builds returned by the api.
+    pub builds_fetched: AtomicU64,
+    /// The number of builds skipped because they were already yielded.
+    pub builds_deduplicated: AtomicU64,
+    /// The number of failed requests.
+    pub api_errors: AtomicU64,
+    /// The number of retried requests.
+    pub retries: AtomicU64,
+}
+
+/// The responses of the configuration endpoints, keyed by url.
+#[cfg(feature = "cache")]
+struct ResponseCache {
+    ttl: Duration,
+    entries: std::sync::Mutex<HashMap<String, (std::time::Instant, serde_json::Value)>>,
+}
+
+#[cfg(feature = "cache")]
+impl ResponseCache {
+    fn new(ttl: Duration) -> Self {
+        ResponseCache {
+            ttl,
+            entries: std::sync::Mutex::new(HashMap::new()),
         }
     }
 
-    /// Produce a stream of unique build.
-    pub fn builds_stream(&self) -> impl Stream<Item = Build> + '_ {
-        let mut offset = 0;
-        let mut known_builds = HashSet::new();
-        stream! {
-            loop {
-                let retry_strategy = ExponentialBackoff::from_millis(10).max_delay(Duration::from_secs(13))
-                    .map(jitter).take(10);
-                let action = || self.builds(offset, 20);
-                let builds = Retry::spawn(retry_strategy, action).await.unwrap();
-                offset += builds.len() as u32;
-                for build_result in builds {
-                    match build_result {
-                        Ok(build) if known_builds.contains(&build.uuid)=> {
-                            // The page moved between request, we skip the known build
-                            // perhaps we should reset the offset to catchup the new one?
-                        },
-                        Ok(build) => {
-                            // Keep track of yieled build to avoid duplicate
-                            known_builds.insert(build.uuid.clone());
-                            yield build;
-                        },
-                        Err(e) => {
-                            error!("Failed to decode build: {:?}", e)
-                        }
-                    }
-                }
+    /// Get a response that is not expired.
+    fn get(&self, url: &str) -> Option<serde_json::Value> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(url) {
+            Some((created, value)) if created.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(url);
+                None
             }
+            None => None,
         }
     }
 
-    /// Get latest builds with optional decoding error.
-    pub async fn builds(
-        &self,
-        skip: u32,
-        limit: u32,
-    ) -> Result<Vec<serde_json::Result<Build>>, reqwest::Error> {
-        let mut url = self.api.join("builds").unwrap();
-        url.query_pairs_mut()
-            .append_pair("complete", "true")
-            .append_pair("skip", &skip.to_string())
-            .append_pair("limit", &limit.to_string());
-        debug!("Querying build {}", url);
-        let resp = self.client.get(url).send().await?;
-        let builds: Vec<serde_json::Value> = resp.json().await?;
-        Ok(builds.iter().map(|b| Build::deserialize(b)).collect())
+    fn insert(&self, url: &str, value: serde_json::Value) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(url.to_string(), (std::time::Instant::now(), value));
     }
 
-    /// Get latest builds (and panic on decoding error).
-    pub async fn builds_unsafe(&self) -> Result<Vec<Build>, reqwest::Error> {
-        let builds = self.builds(0, 20).await?;
-        let builds: Result<Vec<Build>, _> = builds.into_iter().collect();
-        Ok(builds.expect("Invalid build json"))
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
     }
 }
 
-/// A Build result.
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct Build {
-    /// The build unique id.
-    pub uuid: String,
-    /// The job name.
-    pub job_name: String,
-    /// The job result.
-    pub result: String,
-    /// The start time.
-    #[serde(with = "python_utc_without_trailing_z")]
-    pub start_time: DateTime<Utc>,
-    /// The end time.
-    #[serde(with = "python_utc_without_trailing_z")]
-    pub end_time: DateTime<Utc>,
-    /// The job duration in second.
-    #[serde(with = "rounded_float")]
-    pub duration: u32,
-    /// The job voting status.
-    pub voting: bool,
-    /// The log url.
-    pub log_url: Option<String>,
-    /// The build artifacts.
-    pub artifacts: Vec<Artifact>,
-    /// The change's project name.
-    pub project: String,
-    /// The change's branch name.
-    pub branch: String,
-    /// The build pipeline.
-    pub pipeline: String,
-    /// The change (or PR) number.
-    pub change: Option<u64>,
-    /// The patchset number (or PR commit).
-    pub patchset: Option<String>,
-    /// The change ref.
-    #[serde(rename = "ref")]
-    pub change_ref: String,
-    /// The internal event id.
-    pub event_id: String,
+/// The number of builds queried per request by the streams.
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// The number of recent builds remembered by the streams to skip duplicates.
+const DEFAULT_DEDUP_WINDOW: usize = 1000;
+
+/// The condition that ends a builds stream.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum StreamEnd {
+    /// Keep on querying the next page.
+    Never,
+    /// Stop at the first empty page.
+    EmptyPage,
+    /// Stop after the first page that is smaller than the page size.
+    ShortPage,
 }
 
-/// A Build artifact.
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct Artifact {
-    /// The artifact name.
-    pub name: String,
-    /// The artifact url.
-    pub url: String,
+/// The builds stream configuration.
+#[derive(Debug, Clone)]
+struct StreamConfig {
+    /// The condition that ends the stream.
+    end: StreamEnd,
+    /// The token to stop the stream after the current page.
+    token: CancellationToken,
+    /// The number of recent build uuids kept to skip the duplicates.
+    dedup_window: usize,
+    /// The maximum number of pages to query.
+    max_pages: Option<u32>,
+    /// The recovery of a failed page, the client retry strategy is used when unset.
+    on_error: Option<ErrorStrategy>,
 }
 
-// Copy pasta from https://serde.rs/custom-date-format.html
-mod python_utc_without_trailing_z {
-    use chrono::{DateTime, TimeZone, Utc};
-    use serde::{self, Deserialize, Deserializer, Serializer};
+impl StreamConfig {
+    fn new(end: StreamEnd) -> Self {
+        StreamConfig {
+            end,
+            token: CancellationToken::new(),
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            max_pages: None,
+            on_error: None,
+        }
+    }
+}
 
-    const FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+/// A bounded set of the most recent build uuids.
+struct DedupWindow {
+    capacity: usize,
+    uuids: HashSet<String>,
+    order: VecDeque<String>,
+}
 
-    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let s = format!("{}", date.format(FORMAT));
-        serializer.serialize_str(&s)
+impl DedupWindow {
+    fn new(capacity: usize) -> Self {
+        DedupWindow {
+            capacity,
+            uuids: HashSet::new(),
+            order: VecDeque::new(),
+        }
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s = String::deserialize(deserializer)?;
-        Utc.datetime_from_str(&s, FORMAT)
-            .map_err(serde::de::Error::custom)
+    fn contains(&self, uuid: &str) -> bool {
+        self.uuids.contains(uuid)
+    }
+
+    /// Remember an uuid, forgetting the oldest one when the window is full.
+    fn insert(&mut self, uuid: String) {
+        if self.capacity == 0 || !self.uuids.insert(uuid.clone()) {
+            return;
+        }
+        self.order.push_back(uuid);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.uuids.remove(&oldest);
+            }
+        }
     }
 }
 
-// For some reason, durations are sometime provided as f32, e.g. `42.0`
-mod rounded_float {
-    use serde::{self, Deserialize, Deserializer, Serializer};
+/// Convert the response error status to a ZuulError.
+fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, ZuulError> {
+    let status = resp.status();
+    match status {
+        reqwest::StatusCode::NOT_FOUND => Err(ZuulError::NotFound),
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+            Err(ZuulError::Unauthorized)
+        }
+        _ if status.is_server_error() => Err(ZuulError::ServerError(status.as_u16())),
+        _ => Ok(resp.error_for_status()?),
+    }
+}
 
-    pub fn serialize<S>(duration: &u32, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_u32(*duration)
+/// Decode a json value, keeping track of the object uuid on failure.
+fn decode<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, ZuulError> {
+    let uuid = value
+        .get("uuid")
+        .and_then(|uuid| uuid.as_str())
+        .unwrap_or_default()
+        .to_string();
+    serde_json::from_value(value).map_err(|source| ZuulError::Decode { uuid, source })
+}
+
+/// Check if a raw build has a result, zuul omits it while the build is running.
+fn has_result(build: &serde_json::Value) -> bool {
+    build
+        .get("result")
+        .and_then(|result| result.as_str())
+        .is_some_and(|result| !result.is_empty())
+}
+
+/// Check if all the builds of a raw buildset have a result.
+fn has_completed(buildset: &serde_json::Value) -> bool {
+    match buildset.get("builds").and_then(|builds| builds.as_array()) {
+        Some(builds) if !builds.is_empty() => builds.iter().all(has_result),
+        _ => has_result(buildset),
     }
+}
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<u32, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let v = f32::deserialize(deserializer)?;
-        Ok(v as u32)
+/// Read a response body chunk by chunk, the stream ends after yielding an error.
+fn response_chunks(
+    mut resp: reqwest::Response,
+) -> impl Stream<Item = Result<bytes::Bytes, ZuulError>> {
+    stream! {
+        loop {
+            match resp.chunk().await {
+                Ok(Some(chunk)) => yield Ok(chunk),
+                Ok(None) => break,
+                Err(e) => {
+                    yield Err(e.into());
+                    break;
+                }
+            }
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::{Duration, NaiveDateTime};
-    use futures_util::pin_mut;
-    use futures_util::stream::StreamExt;
+/// Decode a server-sent event.
+fn decode_event(event: eventsource_stream::Event) -> Result<ZuulEvent, ZuulError> {
+    let data = || {
+        serde_json::from_str(&event.data).map_err(|source| ZuulError::Decode {
+            uuid: event.id.clone(),
+            source,
+        })
+    };
+    Ok(match event.event.as_str() {
+        "build-started" => ZuulEvent::BuildStarted(decode(data()?)?),
+        "build-completed" => ZuulEvent::BuildCompleted(decode(data()?)?),
+        "pipeline-changed" => ZuulEvent::PipelineChanged(data()?),
+        _ => ZuulEvent::Unknown(event.event),
+    })
+}
 
-    #[test]
-    fn it_parse_url() {
-        let assert_url =
-            |url, expected: &str| assert_eq!(parse_root_url(url).unwrap().to_string(), expected);
-        assert_url("https://example.com", "https://example.com/");
-        assert_url("https://example.com/", "https://example.com/");
-        assert_url("https://example.com/api", "https://example.com/api/");
-        assert_url("https://example.com/api/", "https://example.com/api/");
+/// Stop a builds stream at the first error.
+fn until_error<'a>(
+    builds: impl Stream<Item = Result<Build, ZuulError>> + 'a,
+) -> impl Stream<Item = Build> + 'a {
+    stream! {
+        for await build in builds {
+            match build {
+                Ok(build) => yield build,
+                Err(e) => {
+                    error!("Failed to get builds: {}", e);
+                    break;
+                }
+            }
+        }
     }
+}
 
-    fn make_build(uuid: &str, end_time: DateTime<Utc>) -> Build {
-        Build {
-            uuid: String::from(uuid),
-            job_name: "job".to_string(),
-            result: "SUCCESS".to_string(),
-            start_time: end_time + Duration::minutes(-42),
-            end_time,
-            duration: 42,
-            voting: true,
-            log_url: Some("http://localhost/".to_string() + &String::from(uuid)),
-            artifacts: [].to_vec(),
-            project: "project".to_string(),
-            branch: "main".to_string(),
-            pipeline: "check".to_string(),
-            change: Some(42),
-            patchset: None,
-            change_ref: "head".to_string(),
-            event_id: "uuid".to_string(),
+/// Spawn a task feeding a builds stream to a channel, until the receiver is dropped or the stream fails.
+fn spawn_to_channel(
+    builds: impl Stream<Item = Result<Build, ZuulError>> + Send + 'static,
+    capacity: usize,
+) -> (JoinHandle<()>, mpsc::Receiver<Build>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let handle = tokio::spawn(async move {
+        futures_util::pin_mut!(builds);
+        while let Some(build) = builds.next().await {
+            match build {
+                Ok(build) => {
+                    if tx.send(build).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("Builds tail failed: {}", e);
+                    break;
+                }
+            }
         }
+    });
+    (handle, rx)
+}
+
+/// Helper function to validate the api url and creates a client.
+///
+/// # Panics
+///
+/// Panics if the http client can't be initialized, e.g. when the TLS backend fails to load,
+/// like `reqwest::Client::new`. Use [ZuulClientBuilder::build] to handle this error.
+pub fn create_client(api: &str) -> Result<Zuul, ParseError> {
+    match ZuulClientBuilder::new(api).build() {
+        Ok(client) => Ok(client),
+        Err(ZuulError::InvalidUrl(e)) => Err(e),
+        Err(e) => panic!("Failed to create the http client: {}", e),
     }
+}
 
-    /// Helper function to drop milli second from a DateTime so that the json encoding round trip
-    fn drop_milli(dt: DateTime<Utc>) -> DateTime<Utc> {
-        let ts = dt.timestamp();
-        DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(ts, 0), Utc)
+/// The client configuration file.
+///
+/// ```toml
+/// url = "https://zuul.example.org/api/"
+/// token = "secret"
+/// tenant = "local"
+/// ```
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ZuulConfig {
+    /// The zuul api url.
+    pub url: String,
+    /// The authentication token.
+    pub token: Option<String>,
+    /// The default tenant.
+    pub tenant: Option<String>,
+}
+
+impl ZuulConfig {
+    /// Load the configuration from a toml file.
+    pub fn from_file(path: &std::path::Path) -> Result<ZuulConfig, ZuulError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ZuulError::InvalidConfig(format!("could not read {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&content).map_err(|e| {
+            ZuulError::InvalidConfig(format!("could not parse {}: {}", path.display(), e))
+        })
     }
 
-    #[tokio::test]
-    async fn it_stream_builds() {
-        use env_logger;
-        env_logger::init();
-        use httpmock::prelude::*;
-        let server = MockServer::start();
+    /// Load the configuration from `~/.config/zuul/client.toml`.
+    pub fn from_default_path() -> Result<ZuulConfig, ZuulError> {
+        let home = std::env::var("HOME")
+            .map_err(|_| ZuulError::InvalidConfig("HOME not set".to_string()))?;
+        ZuulConfig::from_file(&std::path::Path::new(&home).join(".config/zuul/client.toml"))
+    }
+}
 
-        let now = drop_milli(Utc::now());
-        let b0 = make_build("42", now);
-        let b1 = make_build("build1", now);
-        let b2 = make_build("build2", now);
-        let b3 = make_build("build3", now);
-        // Simulate a sliding page
-        let page1 = serde_json::json!([b1.clone(), b2.clone()].to_vec());
-        let page2 = serde_json::json!([b2.clone(), b3.clone()].to_vec());
+/// Encrypt a secret with a project public key, see [Zuul::get_project_public_key].
+///
+/// The plaintext is split in chunks that fit the key size, each chunk is encrypted with
+/// PKCS#1 OAEP and base64 encoded, one per line, as expected by the `!encrypted/pkcs1-oaep` tag.
+pub fn encrypt_secret(pem: &str, plaintext: &[u8]) -> Result<String, ZuulError> {
+    use base64::Engine;
+    use rsa::pkcs1::DecodeRsaPublicKey;
+    use rsa::pkcs8::DecodePublicKey;
+    use rsa::traits::PublicKeyParts;
 
-        let m0 = server.mock(|when, then| {
-            when.method(GET).path("/builds").query_param("limit", "1");
-            then.status(200).json_body(serde_json::json!([b0]));
-        });
+    let key = rsa::RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| rsa::RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|e| ZuulError::InvalidConfig(format!("invalid public key: {}", e)))?;
+    // The OAEP padding uses two sha1 digests and two bytes.
+    let chunk_size = key
+        .size()
+        .checked_sub(2 * 20 + 2)
+        .filter(|size| *size > 0)
+        .ok_or_else(|| ZuulError::InvalidConfig("public key too small".to_string()))?;
+    let mut rng = rsa::rand_core::OsRng;
+    plaintext
+        .chunks(chunk_size)
+        .map(|chunk| {
+            key.encrypt(&mut rng, rsa::Oaep::new::<sha1::Sha1>(), chunk)
+                .map(|ciphertext| base64::engine::general_purpose::STANDARD.encode(ciphertext))
+                .map_err(|e| ZuulError::InvalidConfig(format!("encryption failed: {}", e)))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|chunks| chunks.join("\n"))
+}
+
+/// The client builder.
+///
+/// ```rust, no_run
+/// let client = zuul::ZuulClientBuilder::new("https://zuul.example.org/api/")
+///     .with_auth_token("secret")
+///     .build()
+///     .expect("Invalid client");
+/// ```
+pub struct ZuulClientBuilder {
+    api: String,
+    auth_token: Option<String>,
+    basic_auth: Option<(String, String)>,
+    connect_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    proxy: Option<Url>,
+    no_proxy: Vec<String>,
+    retry: RetryStrategy,
+    root_certificates: Vec<reqwest::Certificate>,
+    accept_invalid_certs: bool,
+    rate_limit: Option<f64>,
+    max_connections: Option<usize>,
+    #[cfg(feature = "cache")]
+    cache_ttl: Option<Duration>,
+}
+
+impl ZuulClientBuilder {
+    /// Create a new builder for the given api url.
+    pub fn new(api: &str) -> Self {
+        ZuulClientBuilder {
+            api: api.to_string(),
+            auth_token: None,
+            basic_auth: None,
+            connect_timeout: None,
+            request_timeout: None,
+            proxy: None,
+            no_proxy: Vec::new(),
+            retry: RetryStrategy::default(),
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+            rate_limit: None,
+            max_connections: None,
+            #[cfg(feature = "cache")]
+            cache_ttl: None,
+        }
+    }
+
+    /// Authenticate the requests with a bearer token.
+    pub fn with_auth_token(mut self, token: &str) -> Self {
+        self.auth_token = Some(token.to_string());
+        self
+    }
+
+    /// Authenticate the requests with http basic auth, e.g. when zuul-web is behind a proxy.
+    pub fn with_basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Set the connection timeout.
+    ///
+    /// There is no timeout by default, and a request may hang forever when the server is unresponsive.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the request timeout, from connecting until the response body is received.
+    ///
+    /// There is no timeout by default, and a request may hang forever when the server is unresponsive.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Send the requests through a http proxy.
+    pub fn with_proxy(mut self, proxy_url: &str) -> Result<Self, ZuulError> {
+        self.proxy = Some(Url::parse(proxy_url)?);
+        Ok(self)
+    }
+
+    /// Set the list of hosts that should not use the proxy.
+    pub fn with_no_proxy(mut self, hosts: &[&str]) -> Self {
+        self.no_proxy = hosts.iter().map(|host| host.to_string()).collect();
+        self
+    }
+
+    /// Set the retry strategy used to recover from api failures.
+    ///
+    /// The strategy applies to the builds streams, [Zuul::builds_tail], [Zuul::get_pipelines]
+    /// and [Zuul::get_buildsets]. The other calls are attempted once and return the first error.
+    pub fn with_retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.retry = strategy;
+        self
+    }
+
+    /// Trust an additional root certificate, e.g. for an internal certificate authority.
+    pub fn with_tls_ca_cert(mut self, cert_pem: &[u8]) -> Result<Self, ZuulError> {
+        let cert = reqwest::Certificate::from_pem(cert_pem)
+            .map_err(|e| ZuulError::InvalidConfig(format!("invalid ca certificate: {}", e)))?;
+        self.root_certificates.push(cert);
+        Ok(self)
+    }
+
+    /// Disable the certificate validation, this is insecure and should only be used for testing.
+    #[deprecated(note = "accepting invalid certificates is insecure, only use it for testing")]
+    pub fn with_danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Set the maximum number of idle connections kept per host, there is no limit by default.
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    /// Throttle the requests to the given rate, shared by all the methods and clones of the client.
+    ///
+    /// There is no limit by default.
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.rate_limit = Some(requests_per_second);
+        self
+    }
+
+    /// Cache the responses of the configuration endpoints, e.g. the tenants or the jobs list.
+    ///
+    /// The cache is shared by the clones of the client, and it is cleared by the write operations.
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Create the client.
+    pub fn build(self) -> Result<Zuul, ZuulError> {
+        let api = parse_root_url(&self.api)?;
+        let authenticated = self.auth_token.is_some();
+        let authorization = match (self.auth_token, self.basic_auth) {
+            (Some(_), Some(_)) => {
+                return Err(ZuulError::InvalidConfig(
+                    "cannot set both basic auth and bearer token".to_string(),
+                ))
+            }
+            (Some(token), None) => Some(format!("Bearer {}", token)),
+            (None, Some((username, password))) => {
+                use base64::Engine;
+                let credentials = format!("{}:{}", username, password);
+                let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+                Some(format!("Basic {}", encoded))
+            }
+            (None, None) => None,
+        };
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(authorization) = authorization {
+            let mut value = reqwest::header::HeaderValue::from_str(&authorization)
+                .map_err(|e| ZuulError::InvalidConfig(format!("invalid authorization: {}", e)))?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            let no_proxy = reqwest::NoProxy::from_string(&self.no_proxy.join(","));
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?.no_proxy(no_proxy));
+        }
+        if let Some(max_connections) = self.max_connections {
+            builder = builder.pool_max_idle_per_host(max_connections);
+        }
+        for cert in self.root_certificates {
+            builder = builder.add_root_certificate(cert);
+        }
+        let client = builder
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .build()?;
+        let rate_limiter = match self.rate_limit {
+            Some(rate) => {
+                let quota = Some(rate)
+                    .filter(|rate| rate.is_finite() && *rate > 0.0)
+                    .and_then(|rate| {
+                        governor::Quota::with_period(Duration::from_secs_f64(1.0 / rate))
+                    })
+                    .ok_or_else(|| {
+                        ZuulError::InvalidConfig(format!("invalid rate limit: {}", rate))
+                    })?;
+                Some(Arc::new(governor::RateLimiter::direct(quota)))
+            }
+            None => None,
+        };
+        Ok(Zuul {
+            client,
+            api,
+            retry: self.retry,
+            authenticated,
+            metrics: Arc::default(),
+            rate_limiter,
+            max_page_size: Arc::default(),
+            #[cfg(feature = "cache")]
+            cache: self.cache_ttl.map(|ttl| Arc::new(ResponseCache::new(ttl))),
+        })
+    }
+}
+
+impl Zuul {
+    /// Create a new client
+    pub fn new(api: Url) -> Self {
+        Zuul {
+            client: reqwest::Client::new(),
+            api,
+            retry: RetryStrategy::default(),
+            authenticated: false,
+            metrics: Arc::default(),
+            rate_limiter: None,
+            max_page_size: Arc::default(),
+            #[cfg(feature = "cache")]
+            cache: None,
+        }
+    }
+
+    /// Create a client from the environment variables.
+    ///
+    /// The api url is read from `ZUUL_API_URL`, and the optional `ZUUL_AUTH_TOKEN` and
+    /// `ZUUL_CA_CERT_PATH` configure the authentication and the tls certificate authority.
+    pub fn from_env() -> Result<Self, ZuulError> {
+        let api = std::env::var("ZUUL_API_URL")
+            .map_err(|_| ZuulError::InvalidConfig("ZUUL_API_URL not set".to_string()))?;
+        let mut builder = ZuulClientBuilder::new(&api);
+        if let Ok(token) = std::env::var("ZUUL_AUTH_TOKEN") {
+            builder = builder.with_auth_token(&token);
+        }
+        if let Ok(path) = std::env::var("ZUUL_CA_CERT_PATH") {
+            let cert = std::fs::read(&path)
+                .map_err(|e| ZuulError::InvalidConfig(format!("could not read {}: {}", path, e)))?;
+            builder = builder.with_tls_ca_cert(&cert)?;
+        }
+        builder.build()
+    }
+
+    /// Get the client counters.
+    pub fn metrics(&self) -> Arc<ZuulMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Create a client scoped to a single tenant, the client api needs to be the zuul-web api root.
+    pub fn tenant(&self, name: &str) -> TenantClient {
+        TenantClient {
+            inner: self.clone(),
+            tenant: name.to_string(),
+        }
+    }
+
+    /// Perform a GET request and decode the json response.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(url = %url)))]
+    async fn get_json<T: DeserializeOwned>(&self, url: Url) -> Result<T, ZuulError> {
+        debug!("Querying {}", url);
+        Ok(self.send(self.client.get(url)).await?.json().await?)
+    }
+
+    /// Perform a GET request, using the response cache when it is enabled.
+    async fn get_json_cached<T: DeserializeOwned>(&self, url: Url) -> Result<T, ZuulError> {
+        #[cfg(feature = "cache")]
+        if let Some(cache) = &self.cache {
+            let value = match cache.get(url.as_str()) {
+                Some(value) => {
+                    debug!("Using cached {}", url);
+                    value
+                }
+                None => {
+                    let value: serde_json::Value = self.get_json(url.clone()).await?;
+                    cache.insert(url.as_str(), value.clone());
+                    value
+                }
+            };
+            return decode(value);
+        }
+        self.get_json(url).await
+    }
+
+    /// Clear the response cache, after a write operation.
+    fn invalidate_cache(&self) {
+        #[cfg(feature = "cache")]
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Perform a request, converting the error status to a ZuulError.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, ZuulError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.until_ready().await;
+        }
+        let resp = match request.send().await {
+            Ok(resp) => check_status(resp),
+            Err(e) => Err(e.into()),
+        };
+        if resp.is_err() {
+            self.metrics.api_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        resp
+    }
+
+    /// The delays between attempts, counting the retries.
+    fn retry_delays(&self) -> impl Iterator<Item = Duration> {
+        self.retry_delays_with(&self.retry)
+    }
+
+    /// The delays between attempts of the given strategy, counting the retries.
+    fn retry_delays_with(&self, strategy: &RetryStrategy) -> impl Iterator<Item = Duration> {
+        let metrics = self.metrics.clone();
+        strategy.delays().inspect(move |_| {
+            metrics.retries.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    /// Check that the client is authenticated with a bearer token.
+    fn check_auth(&self) -> Result<(), ZuulError> {
+        match self.authenticated {
+            true => Ok(()),
+            false => Err(ZuulError::Unauthorized),
+        }
+    }
+
+    /// Build a tenant scoped endpoint url.
+    fn tenant_url(&self, tenant: &str, path: &str) -> Result<Url, ZuulError> {
+        Ok(self.api.join(&format!(
+            "tenant/{}/{}",
+            utf8_percent_encode(tenant, PATH_SEGMENT),
+            path
+        ))?)
+    }
+
+    /// Perform a GET request and decode each element of the json list response.
+    async fn get_json_list<T: DeserializeOwned>(
+        &self,
+        url: Url,
+    ) -> Result<Vec<Result<T, ZuulError>>, ZuulError> {
+        let values: Vec<serde_json::Value> = self.get_json(url).await?;
+        Ok(values.into_iter().map(decode).collect())
+    }
+
+    /// Get the list of tenants, the client api needs to be the zuul-web api root.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_tenants(&self) -> Result<Vec<Tenant>, ZuulError> {
+        self.get_json_cached(self.api.join("tenants")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the zuul-web server information, the client api needs to be the zuul-web api root.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_info(&self) -> Result<ZuulInfo, ZuulError> {
+        let resp: InfoResponse = self.get_json_cached(self.api.join("info")?).await?;
+        Ok(resp.info)
+    }
+
+    /// Get the list of projects of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_projects(&self, tenant: &str) -> Result<Vec<Project>, ZuulError> {
+        self.get_json_cached(self.tenant_url(tenant, "projects")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the configuration of a project.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_project_by_name(
+        &self,
+        tenant: &str,
+        project: &str,
+    ) -> Result<ProjectDetail, ZuulError> {
+        let project = encode_project_name(project);
+        self.get_json_cached(self.tenant_url(tenant, &format!("project/{}", project))?)
+            .await
+    }
+
+    /// Get the list of jobs of a tenant, skipping the ones that can't be decoded.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_jobs(&self, tenant: &str) -> Result<Vec<Job>, ZuulError> {
+        let jobs: Vec<serde_json::Value> = self
+            .get_json_cached(self.tenant_url(tenant, "jobs")?)
+            .await?;
+        Ok(record_count(
+            jobs.into_iter()
+                .map(decode)
+                .filter_map(|job| match job {
+                    Ok(job) => Some(job),
+                    Err(e) => {
+                        error!("Failed to decode job: {}", e);
+                        None
+                    }
+                })
+                .collect(),
+        ))
+    }
+
+    /// Get the definition of a job with all its variants.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_job_by_name(&self, tenant: &str, name: &str) -> Result<JobDetail, ZuulError> {
+        let variants: Vec<serde_json::Value> = self
+            .get_json_cached(self.tenant_url(
+                tenant,
+                &format!("job/{}", utf8_percent_encode(name, PATH_SEGMENT)),
+            )?)
+            .await?;
+        let description = variants
+            .iter()
+            .find_map(|variant| variant["description"].as_str())
+            .map(|description| description.to_string());
+        let variants = variants
+            .into_iter()
+            .map(decode)
+            .collect::<Result<Vec<JobVariant>, ZuulError>>()?;
+        if variants.is_empty() {
+            return Err(ZuulError::NotFound);
+        }
+        Ok(JobDetail {
+            name: name.to_string(),
+            description,
+            variants,
+        })
+    }
+
+    /// Get the jobs that would run for a project branch in a pipeline.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_frozen_jobs(
+        &self,
+        tenant: &str,
+        pipeline: &str,
+        project: &str,
+        branch: &str,
+    ) -> Result<Vec<FrozenJob>, ZuulError> {
+        let path = format!(
+            "pipeline/{}/project/{}/branch/{}/freeze-jobs",
+            utf8_percent_encode(pipeline, PATH_SEGMENT),
+            encode_project_name(project),
+            utf8_percent_encode(branch, PATH_SEGMENT)
+        );
+        self.get_json_cached(self.tenant_url(tenant, &path)?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the list of pipelines of a tenant, retrying on failure.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_pipelines(&self, tenant: &str) -> Result<Vec<Pipeline>, ZuulError> {
+        let url = self.tenant_url(tenant, "pipelines")?;
+        let action = || self.get_json_cached(url.clone());
+        Retry::start(self.retry_delays(), action)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the latest buildsets of a tenant, retrying on failure.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_buildsets(
+        &self,
+        tenant: &str,
+        skip: u32,
+        limit: u32,
+    ) -> Result<Vec<Buildset>, ZuulError> {
+        let mut url = self.tenant_url(tenant, "buildsets")?;
+        url.query_pairs_mut()
+            .append_pair("skip", &skip.to_string())
+            .append_pair("limit", &limit.to_string());
+        let action = || self.get_json(url.clone());
+        Retry::start(self.retry_delays(), action)
+            .await
+            .map(record_count)
+    }
+
+    /// Get a single buildset, including its builds.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_buildset(&self, tenant: &str, uuid: &str) -> Result<Buildset, ZuulError> {
+        self.get_json(self.tenant_url(tenant, &format!("buildset/{}", uuid))?)
+            .await
+    }
+
+    /// Get the list of nodes of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_nodes(&self, tenant: &str) -> Result<Vec<Node>, ZuulError> {
+        self.get_json(self.tenant_url(tenant, "nodes")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the list of labels of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_labels(&self, tenant: &str) -> Result<Vec<Label>, ZuulError> {
+        self.get_json_cached(self.tenant_url(tenant, "labels")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the semaphores state of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_semaphores(&self, tenant: &str) -> Result<Vec<SemaphoreInfo>, ZuulError> {
+        self.get_json(self.tenant_url(tenant, "semaphores")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get the configuration errors of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_config_errors(&self, tenant: &str) -> Result<Vec<ConfigError>, ZuulError> {
+        self.get_json_cached(self.tenant_url(tenant, "config-errors")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Create an autohold request, this requires a bearer token.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, req)))]
+    pub async fn create_autohold(
+        &self,
+        tenant: &str,
+        req: &AutoholdRequest,
+    ) -> Result<AutoholdInfo, ZuulError> {
+        self.check_auth()?;
+        let url = self.tenant_url(
+            tenant,
+            &format!("project/{}/autohold", encode_project_name(&req.project)),
+        )?;
+        self.invalidate_cache();
+        debug!("Creating autohold {}", url);
+        Ok(self
+            .send(self.client.post(url).json(req))
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// Get the list of autohold requests, this requires a bearer token.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn list_autoholds(&self, tenant: &str) -> Result<Vec<AutoholdInfo>, ZuulError> {
+        self.check_auth()?;
+        self.get_json(self.tenant_url(tenant, "autohold")?)
+            .await
+            .map(record_count)
+    }
+
+    /// Get an autohold request, this requires a bearer token.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_autohold(&self, tenant: &str, id: &str) -> Result<AutoholdInfo, ZuulError> {
+        self.check_auth()?;
+        self.get_json(self.tenant_url(tenant, &format!("autohold/{}", id))?)
+            .await
+    }
+
+    /// Delete an autohold request, this requires a bearer token.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn delete_autohold(&self, tenant: &str, id: &str) -> Result<(), ZuulError> {
+        self.check_auth()?;
+        let url = self.tenant_url(tenant, &format!("autohold/{}", id))?;
+        self.invalidate_cache();
+        debug!("Deleting autohold {}", url);
+        self.send(self.client.delete(url)).await?;
+        Ok(())
+    }
+
+    /// Move changes to the head of a dependent pipeline queue, this requires a bearer token.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, req)))]
+    pub async fn promote_change(
+        &self,
+        tenant: &str,
+        req: &PromoteRequest,
+    ) -> Result<(), ZuulError> {
+        self.check_auth()?;
+        let url = self.tenant_url(tenant, "promote")?;
+        self.invalidate_cache();
+        debug!("Promoting {:?} {}", req.changes, url);
+        self.send(self.client.post(url).json(req)).await?;
+        Ok(())
+    }
+
+    /// Remove a change from a pipeline queue, this requires a bearer token.
+    ///
+    /// Exactly one of the request change or ref needs to be set.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, req)))]
+    pub async fn dequeue_change(
+        &self,
+        tenant: &str,
+        req: &DequeueRequest,
+    ) -> Result<(), ZuulError> {
+        if req.change.is_some() == req.ref_.is_some() {
+            return Err(ZuulError::InvalidConfig(
+                "dequeue requires either a change or a ref".to_string(),
+            ));
+        }
+        self.check_auth()?;
+        let url = self.tenant_url(
+            tenant,
+            &format!("project/{}/dequeue", encode_project_name(&req.project)),
+        )?;
+        self.invalidate_cache();
+        debug!("Dequeuing {}", url);
+        self.send(self.client.post(url).json(req)).await?;
+        Ok(())
+    }
+
+    /// Add a change to a pipeline, this requires a bearer token.
+    ///
+    /// Exactly one of the request change or ref needs to be set.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, req)))]
+    pub async fn enqueue_change(
+        &self,
+        tenant: &str,
+        req: &EnqueueRequest,
+    ) -> Result<(), ZuulError> {
+        if req.change.is_some() == req.ref_.is_some() {
+            return Err(ZuulError::InvalidConfig(
+                "enqueue requires either a change or a ref".to_string(),
+            ));
+        }
+        self.check_auth()?;
+        let url = self.tenant_url(
+            tenant,
+            &format!("project/{}/enqueue", encode_project_name(&req.project)),
+        )?;
+        self.invalidate_cache();
+        debug!("Enqueuing {}", url);
+        self.send(self.client.post(url).json(req)).await?;
+        Ok(())
+    }
+
+    /// Get the raw status of the pipelines of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_status(&self, tenant: &str) -> Result<serde_json::Value, ZuulError> {
+        self.get_json(self.tenant_url(tenant, "status")?).await
+    }
+
+    /// Get the status of a single pipeline, with its live queues.
+    ///
+    /// The api doesn't provide a pipeline status endpoint, the tenant status is filtered.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_pipeline_status(
+        &self,
+        tenant: &str,
+        pipeline: &str,
+    ) -> Result<PipelineStatus, ZuulError> {
+        self.get_tenant_status(tenant)
+            .await?
+            .pipelines
+            .into_iter()
+            .find(|status| status.name == pipeline)
+            .ok_or(ZuulError::NotFound)
+    }
+
+    /// Get the status of the pipelines of a tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_tenant_status(&self, tenant: &str) -> Result<TenantStatus, ZuulError> {
+        self.get_json(self.tenant_url(tenant, "status")?).await
+    }
+
+    /// Wait for a build to complete, polling its state every `poll_interval`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn wait_for_build(
+        &self,
+        tenant: &str,
+        uuid: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<Build, ZuulError> {
+        let url = self.tenant_url(tenant, &format!("build/{}", uuid))?;
+        let wait = async {
+            loop {
+                let build: serde_json::Value = self.get_json(url.clone()).await?;
+                if has_result(&build) {
+                    return decode(build);
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| ZuulError::Timeout)?
+    }
+
+    /// Wait for all the builds of a buildset to complete, polling its state every `poll_interval`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn wait_for_buildset(
+        &self,
+        tenant: &str,
+        uuid: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<Buildset, ZuulError> {
+        let url = self.tenant_url(tenant, &format!("buildset/{}", uuid))?;
+        let wait = async {
+            loop {
+                let buildset: serde_json::Value = self.get_json(url.clone()).await?;
+                if has_completed(&buildset) {
+                    return decode(buildset);
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| ZuulError::Timeout)?
+    }
+
+    /// Download the console output of a build from the log server.
+    ///
+    /// Returns [ZuulError::NotFound] when the build has no valid log url.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, build), fields(uuid = %build.uuid)))]
+    pub async fn get_build_console_output(&self, build: &Build) -> Result<String, ZuulError> {
+        let url = build
+            .log_url_parsed()
+            .ok_or(ZuulError::NotFound)?
+            .join("job-output.txt")?;
+        debug!("Querying {}", url);
+        Ok(self.send(self.client.get(url)).await?.text().await?)
+    }
+
+    /// Download the zuul manifest of a build, listing its log files.
+    ///
+    /// Returns [ZuulError::NotFound] when the build has no manifest artifact.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, build), fields(uuid = %build.uuid)))]
+    pub async fn fetch_manifest(&self, build: &Build) -> Result<Manifest, ZuulError> {
+        let url = build.zuul_manifest_url().ok_or(ZuulError::NotFound)?;
+        self.get_json(Url::parse(url)?).await
+    }
+
+    /// Download a build artifact.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, artifact), fields(url = %artifact.url)))]
+    pub async fn download_artifact(&self, artifact: &Artifact) -> Result<bytes::Bytes, ZuulError> {
+        let url = Url::parse(&artifact.url)?;
+        debug!("Downloading {}", url);
+        Ok(self.send(self.client.get(url)).await?.bytes().await?)
+    }
+
+    /// Download a build artifact chunk by chunk, to avoid loading large files in memory.
+    ///
+    /// The stream ends after yielding an error.
+    pub fn download_artifact_stream<'a>(
+        &'a self,
+        artifact: &'a Artifact,
+    ) -> impl Stream<Item = Result<bytes::Bytes, ZuulError>> + 'a {
+        stream! {
+            let url = match Url::parse(&artifact.url) {
+                Ok(url) => url,
+                Err(e) => {
+                    yield Err(e.into());
+                    return;
+                }
+            };
+            debug!("Downloading {}", url);
+            match self.send(self.client.get(url)).await {
+                Ok(resp) => {
+                    for await chunk in response_chunks(resp) {
+                        yield chunk;
+                    }
+                }
+                Err(e) => yield Err(e),
+            }
+        }
+    }
+
+    /// Get the PEM encoded public key of a project, used to encrypt its secrets.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_project_public_key(
+        &self,
+        tenant: &str,
+        project: &str,
+    ) -> Result<String, ZuulError> {
+        let url = self.tenant_url(tenant, &format!("key/{}.pub", encode_project_name(project)))?;
+        debug!("Querying {}", url);
+        Ok(self.send(self.client.get(url)).await?.text().await?)
+    }
+
+    /// Produce a stream of the events of a tenant, pushed by the server.
+    ///
+    /// The stream ends after yielding an error.
+    pub fn events_stream<'a>(
+        &'a self,
+        tenant: &'a str,
+    ) -> impl Stream<Item = Result<ZuulEvent, ZuulError>> + 'a {
+        use eventsource_stream::{EventStreamError, Eventsource};
+        stream! {
+            let url = match self.tenant_url(tenant, "events") {
+                Ok(url) => url,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            debug!("Subscribing to {}", url);
+            let request = self
+                .client
+                .get(url)
+                .header(reqwest::header::ACCEPT, "text/event-stream");
+            let resp = match self.send(request).await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            for await event in response_chunks(resp).eventsource() {
+                let event = match event {
+                    Ok(event) => decode_event(event),
+                    Err(EventStreamError::Transport(e)) => Err(e),
+                    Err(e) => Err(ZuulError::InvalidEvent(e.to_string())),
+                };
+                let failed = event.is_err();
+                yield event;
+                if failed {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Produce a continuous stream of unique build.
+    ///
+    /// The stream ends after yielding an error when the api can't be queried.
+    pub fn builds_tail(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_ {
+        self.builds_tail_where(loop_delay, since, |_| true)
+    }
+
+    /// Produce a continuous stream of unique build matching the predicate.
+    ///
+    /// The filtered out builds still advance the since cursor.
+    pub fn builds_tail_where<F>(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+        predicate: F,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_
+    where
+        F: Fn(&Build) -> bool + 'static,
+    {
+        self.tail_builds_query(
+            BuildsQuery::default(),
+            loop_delay,
+            since,
+            predicate,
+            None,
+            None,
+        )
+    }
+
+    /// Produce a continuous stream of unique build, recovering from the api errors with the strategy.
+    pub fn builds_tail_with_error_strategy(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+        on_error: ErrorStrategy,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_ {
+        self.tail_builds_query(
+            BuildsQuery::default(),
+            loop_delay,
+            since,
+            |_| true,
+            None,
+            Some(on_error),
+        )
+    }
+
+    /// Spawn a task feeding the builds tail to a channel.
+    ///
+    /// The task stops when the receiver is dropped or when the api can't be queried.
+    pub fn builds_to_channel(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+        capacity: usize,
+    ) -> (JoinHandle<()>, mpsc::Receiver<Build>) {
+        let client = self.clone();
+        spawn_to_channel(
+            stream! {
+                for await build in client.builds_tail(loop_delay, since) {
+                    yield build;
+                }
+            },
+            capacity,
+        )
+    }
+
+    /// Produce a continuous stream of unique build, resuming from the checkpoint file.
+    ///
+    /// The checkpoint is updated after each batch of builds.
+    pub fn builds_tail_with_checkpoint<'a>(
+        &'a self,
+        loop_delay: Duration,
+        path: &'a std::path::Path,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + 'a {
+        self.tail_builds_checkpoint(BuildsQuery::default(), loop_delay, path)
+    }
+
+    /// Produce a continuous stream of unique build matching the query, resuming from the checkpoint file.
+    fn tail_builds_checkpoint<'a>(
+        &'a self,
+        query: BuildsQuery,
+        loop_delay: Duration,
+        path: &'a std::path::Path,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + 'a {
+        stream! {
+            let since = match path.exists() {
+                true => match BuildsCheckpoint::load_from_file(path) {
+                    Ok(checkpoint) => Some(checkpoint.last_uuid),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                },
+                false => None,
+            };
+            let builds = self.tail_builds_query(query, loop_delay, since, |_| true, Some(path.to_path_buf()), None);
+            for await build in builds {
+                yield build;
+            }
+        }
+    }
+
+    /// Produce a continuous stream of unique build matching the query and the predicate.
+    ///
+    /// The since cursor is saved to the checkpoint path after each batch.
+    fn tail_builds_query<F>(
+        &self,
+        query: BuildsQuery,
+        loop_delay: Duration,
+        since: Option<String>,
+        predicate: F,
+        checkpoint: Option<std::path::PathBuf>,
+        on_error: Option<ErrorStrategy>,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_
+    where
+        F: Fn(&Build) -> bool + 'static,
+    {
+        let mut since = since.clone();
+        let config = StreamConfig {
+            on_error,
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        let strategy = on_error.unwrap_or(ErrorStrategy::Retry(self.retry));
+        stream! {
+            loop {
+                match since.clone() {
+                    Some(uuid) => {
+                        for await (idx, build) in self.try_builds_stream_with(query.clone(), config.clone()).enumerate() {
+                            let build = match build {
+                                Ok(build) => build,
+                                Err(e) => {
+                                    yield Err(e);
+                                    return;
+                                }
+                            };
+                            if idx == 0 {
+                                since = Some(build.uuid.clone());
+                            }
+                            match build.uuid == uuid {
+                                true => break,
+                                false if predicate(&build) => yield Ok(build),
+                                false => debug!("Skipping build {}", build.uuid)
+                            }
+                        }
+                    },
+                    None => {
+                        // get latest build
+                        let mut latest_query = query.clone();
+                        latest_query.limit(1);
+                        let action = || self.query_builds(&latest_query);
+                        let latest = match strategy {
+                            ErrorStrategy::Retry(retry) => Retry::start(self.retry_delays_with(&retry), action).await,
+                            _ => action().await,
+                        };
+                        match latest {
+                            Ok(mut builds) => {
+                                if let Some(Ok(build)) = builds.pop() {
+                                    debug!("Current latest build is {:?}", build);
+                                    since = Some(build.uuid.clone());
+                                }
+                            }
+                            Err(e) if strategy == ErrorStrategy::Skip => {
+                                error!("Could not get the latest build, trying again later: {}", e);
+                            }
+                            Err(e) => {
+                                yield Err(e);
+                                return;
+                            }
+                        }
+                        if since.is_none() && strategy != ErrorStrategy::Skip {
+                            error!("Could not get the latest build");
+                            yield Err(ZuulError::NotFound);
+                            return;
+                        }
+                    }
+                }
+                if let (Some(path), Some(uuid)) = (&checkpoint, &since) {
+                    let checkpoint = BuildsCheckpoint {
+                        last_uuid: uuid.clone(),
+                        timestamp: Utc::now(),
+                    };
+                    if let Err(e) = checkpoint.save_to_file(path) {
+                        error!("Could not save the checkpoint {}: {}", path.display(), e);
+                    }
+                }
+                debug!("Now sleeping {:?}", loop_delay);
+                tokio::time::sleep(loop_delay).await;
+            }
+        }
+    }
+
+    /// Produce a stream of unique build.
+    ///
+    /// The stream ends when the api can't be queried after a few retries.
+    pub fn builds_stream(&self) -> impl Stream<Item = Build> + '_ {
+        self.builds_stream_with_page_size(DEFAULT_PAGE_SIZE)
+    }
+
+    /// Produce a stream of unique build, querying `page_size` builds per request.
+    ///
+    /// The page size is capped at the server maximum, see [ZuulInfo::max_page_size].
+    pub fn builds_stream_with_page_size(&self, page_size: u32) -> impl Stream<Item = Build> + '_ {
+        let mut query = BuildsQuery::default();
+        query.limit(page_size);
+        until_error(self.try_builds_stream(query, StreamEnd::Never))
+    }
+
+    /// Cap the page size at the server maximum, keeping it as is when the info is not available.
+    ///
+    /// The info is queried once per client. It is an optional probe, so its failure is not
+    /// counted as an api error.
+    async fn cap_page_size(&self, page_size: u32) -> u32 {
+        let max_page_size = self
+            .max_page_size
+            .get_or_init(|| async {
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.until_ready().await;
+                }
+                let url = self.api.join("info").ok()?;
+                let resp = check_status(self.client.get(url).send().await.ok()?).ok()?;
+                let info: InfoResponse = resp.json().await.ok()?;
+                info.info.max_page_size
+            })
+            .await;
+        max_page_size.map_or(page_size, |max| page_size.min(max))
+    }
+
+    /// Produce a stream of unique build, until the token is cancelled.
+    ///
+    /// The builds of the page being fetched when the token is cancelled are still yielded.
+    pub fn builds_stream_cancellable(
+        &self,
+        token: CancellationToken,
+    ) -> impl Stream<Item = Build> + '_ {
+        let config = StreamConfig {
+            token,
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        until_error(self.try_builds_stream_with(BuildsQuery::default(), config))
+    }
+
+    /// Produce a stream of unique build, remembering the last `capacity` builds to skip duplicates.
+    ///
+    /// The [Zuul::builds_stream] remembers the last 1000 builds.
+    pub fn builds_stream_with_dedup_window(
+        &self,
+        capacity: usize,
+    ) -> impl Stream<Item = Build> + '_ {
+        let config = StreamConfig {
+            dedup_window: capacity,
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        until_error(self.try_builds_stream_with(BuildsQuery::default(), config))
+    }
+
+    /// Produce a stream of unique build, until a page is smaller than the page size.
+    pub fn builds_stream_finite(&self) -> impl Stream<Item = Build> + '_ {
+        until_error(self.try_builds_stream(BuildsQuery::default(), StreamEnd::ShortPage))
+    }
+
+    /// Produce a stream of unique build, until `max_pages` pages have been queried.
+    pub fn builds_stream_max_pages(&self, max_pages: u32) -> impl Stream<Item = Build> + '_ {
+        let config = StreamConfig {
+            max_pages: Some(max_pages),
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        until_error(self.try_builds_stream_with(BuildsQuery::default(), config))
+    }
+
+    /// Produce a stream of unique build matching the query, until the last page.
+    ///
+    /// The query skip is used as the initial offset.
+    pub fn builds_stream_filtered<'a>(
+        &'a self,
+        query: &'a BuildsQuery,
+    ) -> impl Stream<Item = Build> + 'a {
+        until_error(self.try_builds_stream(query.clone(), StreamEnd::EmptyPage))
+    }
+
+    /// Produce a stream of unique build that ended after the given time.
+    ///
+    /// The builds are yielded in the api order, and the pagination stops at the first older build.
+    pub fn builds_stream_since_time(&self, since: DateTime<Utc>) -> impl Stream<Item = Build> + '_ {
+        until_error(self.try_builds_stream(BuildsQuery::default(), StreamEnd::EmptyPage))
+            .take_while(move |build| futures_util::future::ready(build.end_time >= since))
+    }
+
+    /// Produce a stream of unique build, ending with the error that stopped the stream.
+    fn try_builds_stream(
+        &self,
+        query: BuildsQuery,
+        end: StreamEnd,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_ {
+        self.try_builds_stream_with(query, StreamConfig::new(end))
+    }
+
+    /// Produce a stream of unique build according to the stream configuration.
+    fn try_builds_stream_with(
+        &self,
+        mut query: BuildsQuery,
+        config: StreamConfig,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_ {
+        let mut known_builds = DedupWindow::new(config.dedup_window);
+        let end = config.end;
+        let strategy = config.on_error.unwrap_or(ErrorStrategy::Retry(self.retry));
+        let mut pages = 0;
+        stream! {
+            let page_size = self.cap_page_size(query.limit.unwrap_or(DEFAULT_PAGE_SIZE)).await;
+            query.limit(page_size);
+            let page_size = page_size as usize;
+            while !config.token.is_cancelled() && config.max_pages.is_none_or(|max| pages < max) {
+                pages += 1;
+                let action = || self.query_builds(&query);
+                let builds = match strategy {
+                    ErrorStrategy::Retry(retry) => Retry::start(self.retry_delays_with(&retry), action).await,
+                    _ => action().await,
+                };
+                let builds = match builds {
+                    Ok(builds) => builds,
+                    Err(e) if strategy == ErrorStrategy::Skip => {
+                        error!("Skipping the page at {}: {}", query.skip, e);
+                        query.skip += page_size as u32;
+                        continue;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let page_len = builds.len();
+                if end != StreamEnd::Never && page_len == 0 {
+                    return;
+                }
+                query.skip += builds.len() as u32;
+                self.metrics.builds_fetched.fetch_add(builds.len() as u64, Ordering::Relaxed);
+                for build_result in builds {
+                    match build_result {
+                        Ok(build) if known_builds.contains(&build.uuid)=> {
+                            // The page moved between request, we skip the known build
+                            // perhaps we should reset the offset to catchup the new one?
+                            self.metrics.builds_deduplicated.fetch_add(1, Ordering::Relaxed);
+                        },
+                        Ok(build) => {
+                            // Keep track of yieled build to avoid duplicate
+                            known_builds.insert(build.uuid.clone());
+                            yield Ok(build);
+                        },
+                        Err(e) => {
+                            error!("Failed to decode build: {}", e)
+                        }
+                    }
+                }
+                if end == StreamEnd::ShortPage && page_len < page_size {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Get the builds matching the query with optional decoding error.
+    async fn query_builds(
+        &self,
+        query: &BuildsQuery,
+    ) -> Result<Vec<Result<Build, ZuulError>>, ZuulError> {
+        self.get_json_list(query.url(&self.api)?).await
+    }
+
+    /// Get a page of builds matching the query, skipping the ones that can't be decoded.
+    ///
+    /// The total count is read from the `X-Total-Count` header, when the api provides it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, query), fields(tenant = ?query.tenant, skip = query.skip, limit = ?query.limit)))]
+    pub async fn get_builds_page(&self, query: &BuildsQuery) -> Result<Page<Build>, ZuulError> {
+        let url = query.url(&self.api)?;
+        debug!("Querying {}", url);
+        let resp = self.send(self.client.get(url)).await?;
+        let total_count = resp
+            .headers()
+            .get("x-total-count")
+            .and_then(|count| count.to_str().ok())
+            .and_then(|count| count.parse().ok());
+        let values: Vec<serde_json::Value> = resp.json().await?;
+        let items = values
+            .into_iter()
+            .filter_map(|value| match decode(value) {
+                Ok(build) => Some(build),
+                Err(e) => {
+                    error!("Failed to decode build: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Ok(Page { items, total_count })
+    }
+
+    /// Count the builds matching the query.
+    ///
+    /// The count is read from the `X-Total-Count` header of a single build request.
+    /// When the api doesn't provide the header, all the pages are fetched and the
+    /// decoded builds are counted, which is much slower. The query max_results and limit
+    /// are ignored, the total is always counted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, query), fields(tenant = ?query.tenant, skip = query.skip, limit = ?query.limit)))]
+    pub async fn builds_count(&self, query: &BuildsQuery) -> Result<u64, ZuulError> {
+        let mut count_query = query.clone();
+        count_query.skip(0).limit(1);
+        match self.get_builds_page(&count_query).await?.total_count {
+            Some(count) => Ok(count),
+            None => {
+                debug!("The api didn't provide the total count, fetching all the builds");
+                let mut query = query.clone();
+                query.skip(0);
+                query.limit = None;
+                query.max_results = None;
+                Ok(self.fetch_all_builds(&query).await?.len() as u64)
+            }
+        }
+    }
+
+    /// Get all the builds matching the query, page by page until the last one.
+    ///
+    /// Use the query max_results to limit the number of builds, and the query concurrency
+    /// to request multiple pages in parallel.
+    /// The query limit defaults to 50 builds per page, capped at [ZuulInfo::max_page_size].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, query), fields(tenant = ?query.tenant, skip = query.skip, limit = ?query.limit, result_count = tracing::field::Empty)))]
+    pub async fn fetch_all_builds(&self, query: &BuildsQuery) -> Result<Vec<Build>, ZuulError> {
+        use futures_util::TryStreamExt;
+        let mut query = query.clone();
+        let page_size = self
+            .cap_page_size(query.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+            .await;
+        let concurrency = query.concurrency.unwrap_or(1).max(1);
+        let mut result = Vec::new();
+        loop {
+            // The next pages, assuming the previous ones are full
+            let mut remaining = query
+                .max_results
+                .map(|max| max.saturating_sub(result.len() as u32));
+            let mut skip = query.skip;
+            let mut pages = Vec::new();
+            for _ in 0..concurrency {
+                let limit = remaining.map_or(page_size, |remaining| page_size.min(remaining));
+                if limit == 0 {
+                    break;
+                }
+                let mut page = query.clone();
+                page.skip(skip).limit(limit);
+                pages.push(page);
+                skip += limit;
+                remaining = remaining.map(|remaining| remaining - limit);
+            }
+            if pages.is_empty() {
+                break;
+            }
+            let mut responses: Vec<(usize, Vec<Result<Build, ZuulError>>)> =
+                futures_util::stream::iter(pages.iter().enumerate())
+                    .map(|(idx, page)| async move {
+                        self.query_builds(page).await.map(|builds| (idx, builds))
+                    })
+                    .buffer_unordered(concurrency)
+                    .try_collect()
+                    .await?;
+            responses.sort_by_key(|(idx, _)| *idx);
+            let mut last_page = false;
+            for (idx, builds) in responses {
+                if builds.is_empty() {
+                    last_page = true;
+                    break;
+                }
+                let short_page = builds.len() < pages[idx].limit.unwrap_or_default() as usize;
+                query.skip += builds.len() as u32;
+                for build in builds {
+                    match build {
+                        Ok(build) => result.push(build),
+                        Err(e) => error!("Failed to decode build: {}", e),
+                    }
+                }
+                if short_page {
+                    // The offsets of the following pages are wrong, they are requested again
+                    break;
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        if let Some(max) = query.max_results {
+            // The server may return more builds than requested
+            result.truncate(max as usize);
+        }
+        Ok(record_count(result))
+    }
+
+    /// Get all the builds of a change, optionally restricted to a patchset.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn get_builds_for_change(
+        &self,
+        tenant: &str,
+        change: u64,
+        patchset: Option<&str>,
+    ) -> Result<Vec<Build>, ZuulError> {
+        let mut query = BuildsQuery::default();
+        query.tenant(tenant).change(change);
+        if let Some(patchset) = patchset {
+            query.patchset(patchset);
+        }
+        self.fetch_all_builds(&query).await.map(record_count)
+    }
+
+    /// Get the most recent completed build of a job on a branch, if any.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_latest_build_for_job(
+        &self,
+        tenant: &str,
+        job: &str,
+        branch: &str,
+    ) -> Result<Option<Build>, ZuulError> {
+        self.latest_build_for_job(tenant, job, branch, None).await
+    }
+
+    /// Get the most recent successful build of a job on a branch, if any.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn last_successful_build_for_job(
+        &self,
+        tenant: &str,
+        job: &str,
+        branch: &str,
+    ) -> Result<Option<Build>, ZuulError> {
+        self.latest_build_for_job(tenant, job, branch, Some("SUCCESS"))
+            .await
+    }
+
+    /// Get the most recent failed build of a job on a branch, if any.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn last_failed_build_for_job(
+        &self,
+        tenant: &str,
+        job: &str,
+        branch: &str,
+    ) -> Result<Option<Build>, ZuulError> {
+        self.latest_build_for_job(tenant, job, branch, Some("FAILURE"))
+            .await
+    }
+
+    /// Get the most recent completed build of a job on a branch with an optional result filter.
+    async fn latest_build_for_job(
+        &self,
+        tenant: &str,
+        job: &str,
+        branch: &str,
+        result: Option<&str>,
+    ) -> Result<Option<Build>, ZuulError> {
+        let mut query = BuildsQuery::default();
+        query
+            .tenant(tenant)
+            .job_name(job)
+            .branch(branch)
+            .skip(0)
+            .limit(1);
+        if let Some(result) = result {
+            query.result(result);
+        }
+        self.query_builds(&query)
+            .await?
+            .into_iter()
+            .next()
+            .transpose()
+    }
+
+    /// Get all the builds matching the query, grouped by buildset uuid.
+    ///
+    /// The builds without a buildset uuid are skipped.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, query), fields(tenant = ?query.tenant, skip = query.skip, limit = ?query.limit)))]
+    pub async fn get_builds_grouped_by_buildset(
+        &self,
+        query: &BuildsQuery,
+    ) -> Result<HashMap<String, Vec<Build>>, ZuulError> {
+        let mut groups: HashMap<String, Vec<Build>> = HashMap::new();
+        for build in self.fetch_all_builds(query).await? {
+            match build.buildset_uuid.clone() {
+                Some(uuid) => groups.entry(uuid).or_default().push(build),
+                None => debug!("Skipping build {} without buildset", build.uuid),
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Get latest builds with optional decoding error.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn builds(
+        &self,
+        skip: u32,
+        limit: u32,
+    ) -> Result<Vec<Result<Build, ZuulError>>, ZuulError> {
+        self.query_builds(BuildsQuery::default().skip(skip).limit(limit))
+            .await
+            .map(record_count)
+    }
+
+    /// Get a single build.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_build(&self, uuid: &str) -> Result<Build, ZuulError> {
+        self.get_json(self.api.join(&format!("build/{}", uuid))?)
+            .await
+    }
+
+    /// Get latest builds (and fail on decoding error).
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(result_count = tracing::field::Empty)))]
+    pub async fn builds_unsafe(&self) -> Result<Vec<Build>, ZuulError> {
+        self.builds(0, 20)
+            .await?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map(record_count)
+    }
+}
+
+/// A client scoped to a single tenant, see [Zuul::tenant].
+#[derive(Clone)]
+pub struct TenantClient {
+    inner: Zuul,
+    tenant: String,
+}
+
+impl std::ops::Deref for TenantClient {
+    type Target = Zuul;
+
+    fn deref(&self) -> &Zuul {
+        &self.inner
+    }
+}
+
+impl TenantClient {
+    /// The tenant name.
+    pub fn name(&self) -> &str {
+        &self.tenant
+    }
+
+    /// The tenant builds query.
+    fn query(&self) -> BuildsQuery {
+        let mut query = BuildsQuery::default();
+        query.tenant(&self.tenant);
+        query
+    }
+
+    /// Get the list of projects.
+    pub async fn get_projects(&self) -> Result<Vec<Project>, ZuulError> {
+        self.inner.get_projects(&self.tenant).await
+    }
+
+    /// Get the configuration of a project.
+    pub async fn get_project_by_name(&self, project: &str) -> Result<ProjectDetail, ZuulError> {
+        self.inner.get_project_by_name(&self.tenant, project).await
+    }
+
+    /// Get the list of jobs, skipping the ones that can't be decoded.
+    pub async fn get_jobs(&self) -> Result<Vec<Job>, ZuulError> {
+        self.inner.get_jobs(&self.tenant).await
+    }
+
+    /// Get the definition of a job with all its variants.
+    pub async fn get_job_by_name(&self, name: &str) -> Result<JobDetail, ZuulError> {
+        self.inner.get_job_by_name(&self.tenant, name).await
+    }
+
+    /// Get the jobs that would run for a project branch in a pipeline.
+    pub async fn get_frozen_jobs(
+        &self,
+        pipeline: &str,
+        project: &str,
+        branch: &str,
+    ) -> Result<Vec<FrozenJob>, ZuulError> {
+        self.inner
+            .get_frozen_jobs(&self.tenant, pipeline, project, branch)
+            .await
+    }
+
+    /// Get the list of pipelines, retrying on failure.
+    pub async fn get_pipelines(&self) -> Result<Vec<Pipeline>, ZuulError> {
+        self.inner.get_pipelines(&self.tenant).await
+    }
+
+    /// Get the latest buildsets, retrying on failure.
+    pub async fn get_buildsets(&self, skip: u32, limit: u32) -> Result<Vec<Buildset>, ZuulError> {
+        self.inner.get_buildsets(&self.tenant, skip, limit).await
+    }
+
+    /// Get a single buildset, including its builds.
+    pub async fn get_buildset(&self, uuid: &str) -> Result<Buildset, ZuulError> {
+        self.inner.get_buildset(&self.tenant, uuid).await
+    }
+
+    /// Get the list of nodes.
+    pub async fn get_nodes(&self) -> Result<Vec<Node>, ZuulError> {
+        self.inner.get_nodes(&self.tenant).await
+    }
+
+    /// Get the list of labels.
+    pub async fn get_labels(&self) -> Result<Vec<Label>, ZuulError> {
+        self.inner.get_labels(&self.tenant).await
+    }
+
+    /// Get the semaphores state.
+    pub async fn get_semaphores(&self) -> Result<Vec<SemaphoreInfo>, ZuulError> {
+        self.inner.get_semaphores(&self.tenant).await
+    }
+
+    /// Get the configuration errors.
+    pub async fn get_config_errors(&self) -> Result<Vec<ConfigError>, ZuulError> {
+        self.inner.get_config_errors(&self.tenant).await
+    }
+
+    /// Create an autohold request, this requires a bearer token.
+    pub async fn create_autohold(&self, req: &AutoholdRequest) -> Result<AutoholdInfo, ZuulError> {
+        self.inner.create_autohold(&self.tenant, req).await
+    }
+
+    /// Get the list of autohold requests, this requires a bearer token.
+    pub async fn list_autoholds(&self) -> Result<Vec<AutoholdInfo>, ZuulError> {
+        self.inner.list_autoholds(&self.tenant).await
+    }
+
+    /// Get an autohold request, this requires a bearer token.
+    pub async fn get_autohold(&self, id: &str) -> Result<AutoholdInfo, ZuulError> {
+        self.inner.get_autohold(&self.tenant, id).await
+    }
+
+    /// Delete an autohold request, this requires a bearer token.
+    pub async fn delete_autohold(&self, id: &str) -> Result<(), ZuulError> {
+        self.inner.delete_autohold(&self.tenant, id).await
+    }
+
+    /// Move changes to the head of a dependent pipeline queue, this requires a bearer token.
+    pub async fn promote_change(&self, req: &PromoteRequest) -> Result<(), ZuulError> {
+        self.inner.promote_change(&self.tenant, req).await
+    }
+
+    /// Remove a change from a pipeline queue, this requires a bearer token.
+    pub async fn dequeue_change(&self, req: &DequeueRequest) -> Result<(), ZuulError> {
+        self.inner.dequeue_change(&self.tenant, req).await
+    }
+
+    /// Add a change to a pipeline, this requires a bearer token.
+    pub async fn enqueue_change(&self, req: &EnqueueRequest) -> Result<(), ZuulError> {
+        self.inner.enqueue_change(&self.tenant, req).await
+    }
+
+    /// Get the raw status of the pipelines.
+    pub async fn get_status(&self) -> Result<serde_json::Value, ZuulError> {
+        self.inner.get_status(&self.tenant).await
+    }
+
+    /// Get the status of the pipelines.
+    pub async fn get_tenant_status(&self) -> Result<TenantStatus, ZuulError> {
+        self.inner.get_tenant_status(&self.tenant).await
+    }
+
+    /// Get the status of a single pipeline, with its live queues.
+    pub async fn get_pipeline_status(&self, pipeline: &str) -> Result<PipelineStatus, ZuulError> {
+        self.inner.get_pipeline_status(&self.tenant, pipeline).await
+    }
+
+    /// Wait for a build to complete, polling its state every `poll_interval`.
+    pub async fn wait_for_build(
+        &self,
+        uuid: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<Build, ZuulError> {
+        self.inner
+            .wait_for_build(&self.tenant, uuid, poll_interval, timeout)
+            .await
+    }
+
+    /// Wait for all the builds of a buildset to complete, polling its state every `poll_interval`.
+    pub async fn wait_for_buildset(
+        &self,
+        uuid: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<Buildset, ZuulError> {
+        self.inner
+            .wait_for_buildset(&self.tenant, uuid, poll_interval, timeout)
+            .await
+    }
+
+    /// Get the PEM encoded public key of a project, used to encrypt its secrets.
+    pub async fn get_project_public_key(&self, project: &str) -> Result<String, ZuulError> {
+        self.inner
+            .get_project_public_key(&self.tenant, project)
+            .await
+    }
+
+    /// Produce a stream of the events, pushed by the server.
+    pub fn events_stream(&self) -> impl Stream<Item = Result<ZuulEvent, ZuulError>> + '_ {
+        self.inner.events_stream(&self.tenant)
+    }
+
+    /// Get a single build.
+    pub async fn get_build(&self, uuid: &str) -> Result<Build, ZuulError> {
+        let url = self.tenant_url(&self.tenant, &format!("build/{}", uuid))?;
+        self.get_json(url).await
+    }
+
+    /// Get latest builds with optional decoding error.
+    pub async fn builds(
+        &self,
+        skip: u32,
+        limit: u32,
+    ) -> Result<Vec<Result<Build, ZuulError>>, ZuulError> {
+        self.query_builds(self.query().skip(skip).limit(limit))
+            .await
+    }
+
+    /// Get all the builds of a change, optionally restricted to a patchset.
+    pub async fn get_builds_for_change(
+        &self,
+        change: u64,
+        patchset: Option<&str>,
+    ) -> Result<Vec<Build>, ZuulError> {
+        self.inner
+            .get_builds_for_change(&self.tenant, change, patchset)
+            .await
+    }
+
+    /// Get the most recent completed build of a job on a branch, if any.
+    pub async fn get_latest_build_for_job(
+        &self,
+        job: &str,
+        branch: &str,
+    ) -> Result<Option<Build>, ZuulError> {
+        self.inner
+            .get_latest_build_for_job(&self.tenant, job, branch)
+            .await
+    }
+
+    /// Get the most recent successful build of a job on a branch, if any.
+    pub async fn last_successful_build_for_job(
+        &self,
+        job: &str,
+        branch: &str,
+    ) -> Result<Option<Build>, ZuulError> {
+        self.inner
+            .last_successful_build_for_job(&self.tenant, job, branch)
+            .await
+    }
+
+    /// Get the most recent failed build of a job on a branch, if any.
+    pub async fn last_failed_build_for_job(
+        &self,
+        job: &str,
+        branch: &str,
+    ) -> Result<Option<Build>, ZuulError> {
+        self.inner
+            .last_failed_build_for_job(&self.tenant, job, branch)
+            .await
+    }
+
+    /// Get latest builds (and fail on decoding error).
+    pub async fn builds_unsafe(&self) -> Result<Vec<Build>, ZuulError> {
+        self.builds(0, 20).await?.into_iter().collect()
+    }
+
+    /// Produce a stream of unique build.
+    ///
+    /// The stream ends when the api can't be queried after a few retries.
+    pub fn builds_stream(&self) -> impl Stream<Item = Build> + '_ {
+        self.builds_stream_with_page_size(DEFAULT_PAGE_SIZE)
+    }
+
+    /// Produce a stream of unique build, querying `page_size` builds per request.
+    ///
+    /// The page size is capped at the server maximum, see [ZuulInfo::max_page_size].
+    pub fn builds_stream_with_page_size(&self, page_size: u32) -> impl Stream<Item = Build> + '_ {
+        let mut query = self.query();
+        query.limit(page_size);
+        until_error(self.try_builds_stream(query, StreamEnd::Never))
+    }
+
+    /// Produce a stream of unique build, remembering the last `capacity` builds to skip duplicates.
+    pub fn builds_stream_with_dedup_window(
+        &self,
+        capacity: usize,
+    ) -> impl Stream<Item = Build> + '_ {
+        let config = StreamConfig {
+            dedup_window: capacity,
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        until_error(self.try_builds_stream_with(self.query(), config))
+    }
+
+    /// Produce a stream of unique build, until a page is smaller than the page size.
+    pub fn builds_stream_finite(&self) -> impl Stream<Item = Build> + '_ {
+        until_error(self.try_builds_stream(self.query(), StreamEnd::ShortPage))
+    }
+
+    /// Produce a stream of unique build, until `max_pages` pages have been queried.
+    pub fn builds_stream_max_pages(&self, max_pages: u32) -> impl Stream<Item = Build> + '_ {
+        let config = StreamConfig {
+            max_pages: Some(max_pages),
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        until_error(self.try_builds_stream_with(self.query(), config))
+    }
+
+    /// Produce a stream of unique build, until the token is cancelled.
+    pub fn builds_stream_cancellable(
+        &self,
+        token: CancellationToken,
+    ) -> impl Stream<Item = Build> + '_ {
+        let config = StreamConfig {
+            token,
+            ..StreamConfig::new(StreamEnd::Never)
+        };
+        until_error(self.try_builds_stream_with(self.query(), config))
+    }
+
+    /// Produce a stream of unique build that ended after the given time.
+    pub fn builds_stream_since_time(&self, since: DateTime<Utc>) -> impl Stream<Item = Build> + '_ {
+        until_error(self.try_builds_stream(self.query(), StreamEnd::EmptyPage))
+            .take_while(move |build| futures_util::future::ready(build.end_time >= since))
+    }
+
+    /// Produce a continuous stream of unique build.
+    ///
+    /// The stream ends after yielding an error when the api can't be queried.
+    pub fn builds_tail(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_ {
+        self.builds_tail_where(loop_delay, since, |_| true)
+    }
+
+    /// Produce a continuous stream of unique build matching the predicate.
+    pub fn builds_tail_where<F>(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+        predicate: F,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_
+    where
+        F: Fn(&Build) -> bool + 'static,
+    {
+        self.tail_builds_query(self.query(), loop_delay, since, predicate, None, None)
+    }
+
+    /// Produce a continuous stream of unique build, recovering from the api errors with the strategy.
+    pub fn builds_tail_with_error_strategy(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+        on_error: ErrorStrategy,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + '_ {
+        self.tail_builds_query(
+            self.query(),
+            loop_delay,
+            since,
+            |_| true,
+            None,
+            Some(on_error),
+        )
+    }
+
+    /// Produce a continuous stream of unique build, resuming from the checkpoint file.
+    pub fn builds_tail_with_checkpoint<'a>(
+        &'a self,
+        loop_delay: Duration,
+        path: &'a std::path::Path,
+    ) -> impl Stream<Item = Result<Build, ZuulError>> + 'a {
+        self.tail_builds_checkpoint(self.query(), loop_delay, path)
+    }
+
+    /// Spawn a task feeding the builds tail to a channel.
+    pub fn builds_to_channel(
+        &self,
+        loop_delay: Duration,
+        since: Option<String>,
+        capacity: usize,
+    ) -> (JoinHandle<()>, mpsc::Receiver<Build>) {
+        let client = self.clone();
+        spawn_to_channel(
+            stream! {
+                for await build in client.builds_tail(loop_delay, since) {
+                    yield build;
+                }
+            },
+            capacity,
+        )
+    }
+}
+
+/// A pool of clients to query multiple zuul instances.
+#[derive(Clone)]
+pub struct ZuulPool {
+    clients: Vec<Zuul>,
+}
+
+impl ZuulPool {
+    /// Create a pool with a client for each api url.
+    pub fn new(urls: &[&str]) -> Result<Self, ZuulError> {
+        let clients = urls
+            .iter()
+            .map(|url| ZuulClientBuilder::new(url).build())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ZuulPool { clients })
+    }
+
+    /// Produce a continuous stream of unique build from all the clients.
+    ///
+    /// The clients are polled in turn, and a build reported by many instances is only
+    /// yielded once.
+    pub fn builds_stream_merged(&self) -> impl Stream<Item = Build> + '_ {
+        let streams = self
+            .clients
+            .iter()
+            .map(|client| Box::pin(client.builds_stream()));
+        // Remember the recent builds of every client, like each client stream does
+        let mut known_builds = DedupWindow::new(DEFAULT_DEDUP_WINDOW * self.clients.len());
+        futures_util::stream::select_all(streams).filter(move |build| {
+            let new = !known_builds.contains(&build.uuid);
+            if new {
+                known_builds.insert(build.uuid.clone());
+            }
+            futures_util::future::ready(new)
+        })
+    }
+}
+
+/// The position of a builds tail, to resume after a restart.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct BuildsCheckpoint {
+    /// The uuid of the last seen build.
+    pub last_uuid: String,
+    /// The checkpoint creation time.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl BuildsCheckpoint {
+    /// Save the checkpoint, the file is replaced atomically.
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+        use std::io::Write;
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(self)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Load a checkpoint.
+    pub fn load_from_file(path: &std::path::Path) -> Result<Self, ZuulError> {
+        let content = std::fs::read(path).map_err(|e| {
+            ZuulError::InvalidConfig(format!("could not read {}: {}", path.display(), e))
+        })?;
+        serde_json::from_slice(&content).map_err(|e| {
+            ZuulError::InvalidConfig(format!("could not parse {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// A builds query builder to filter the builds server side.
+///
+/// ```rust, no_run
+/// # async fn run(client: zuul::Zuul) -> Result<(), zuul::ZuulError> {
+/// let builds = zuul::BuildsQuery::default()
+///     .project("zuul/zuul")
+///     .result("FAILURE")
+///     .limit(50)
+///     .fetch(&client)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BuildsQuery {
+    project: Option<String>,
+    job_name: Option<String>,
+    branch: Option<String>,
+    pipeline: Option<String>,
+    result: Option<String>,
+    change: Option<u64>,
+    patchset: Option<String>,
+    skip: u32,
+    limit: Option<u32>,
+    max_results: Option<u32>,
+    tenant: Option<String>,
+    concurrency: Option<usize>,
+}
+
+impl BuildsQuery {
+    /// Query the builds of a tenant, the client api needs to be the zuul-web api root.
+    pub fn tenant(&mut self, tenant: &str) -> &mut Self {
+        self.tenant = Some(tenant.to_string());
+        self
+    }
+
+    /// Filter by project name.
+    pub fn project(&mut self, project: &str) -> &mut Self {
+        self.project = Some(project.to_string());
+        self
+    }
+
+    /// Filter by job name.
+    pub fn job_name(&mut self, job_name: &str) -> &mut Self {
+        self.job_name = Some(job_name.to_string());
+        self
+    }
+
+    /// Filter by branch name.
+    pub fn branch(&mut self, branch: &str) -> &mut Self {
+        self.branch = Some(branch.to_string());
+        self
+    }
+
+    /// Filter by pipeline name.
+    pub fn pipeline(&mut self, pipeline: &str) -> &mut Self {
+        self.pipeline = Some(pipeline.to_string());
+        self
+    }
+
+    /// Filter by build result, e.g. `FAILURE`.
+    pub fn result(&mut self, result: &str) -> &mut Self {
+        self.result = Some(result.to_string());
+        self
+    }
+
+    /// Filter by change number.
+    pub fn change(&mut self, change: u64) -> &mut Self {
+        self.change = Some(change);
+        self
+    }
+
+    /// Filter by patchset.
+    pub fn patchset(&mut self, patchset: &str) -> &mut Self {
+        self.patchset = Some(patchset.to_string());
+        self
+    }
+
+    /// Skip the first builds.
+    pub fn skip(&mut self, skip: u32) -> &mut Self {
+        self.skip = skip;
+        self
+    }
+
+    /// Limit the number of builds.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Cap the number of builds returned by [Zuul::fetch_all_builds].
+    pub fn max_results(&mut self, max_results: u32) -> &mut Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Set the number of pages requested in parallel by [Zuul::fetch_all_builds], defaults to 1.
+    pub fn with_concurrency(&mut self, concurrency: usize) -> &mut Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Build the query url.
+    fn url(&self, api: &Url) -> Result<Url, ParseError> {
+        let mut url = match &self.tenant {
+            Some(tenant) => api.join(&format!(
+                "tenant/{}/builds",
+                utf8_percent_encode(tenant, PATH_SEGMENT)
+            ))?,
+            None => api.join("builds")?,
+        };
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("complete", "true");
+            let filters = [
+                ("project", &self.project),
+                ("job_name", &self.job_name),
+                ("branch", &self.branch),
+                ("pipeline", &self.pipeline),
+                ("result", &self.result),
+                ("patchset", &self.patchset),
+            ];
+            for (name, value) in filters.iter() {
+                if let Some(value) = value {
+                    query.append_pair(name, value);
+                }
+            }
+            if let Some(change) = self.change {
+                query.append_pair("change", &change.to_string());
+            }
+            query.append_pair("skip", &self.skip.to_string());
+            if let Some(limit) = self.limit {
+                query.append_pair("limit", &limit.to_string());
+            }
+        }
+        Ok(url)
+    }
+
+    /// Fetch the builds, skipping the ones that can't be decoded.
+    pub async fn fetch(&self, client: &Zuul) -> Result<Vec<Build>, ZuulError> {
+        let builds = client.query_builds(self).await?;
+        Ok(builds
+            .into_iter()
+            .filter_map(|build| match build {
+                Ok(build) => Some(build),
+                Err(e) => {
+                    error!("Failed to decode build: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// A page of a list endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Page<T> {
+    /// The page items.
+    pub items: Vec<T>,
+    /// The total number of items across all the pages, when provided by the api.
+    pub total_count: Option<u64>,
+}
+
+/// The zuul-web server information.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ZuulInfo {
+    /// The zuul version.
+    #[serde(default)]
+    pub zuul_version: String,
+    /// The maximum number of change dependencies.
+    pub max_dependencies_count: Option<u32>,
+    /// Is the api restricted to a single tenant.
+    #[serde(default)]
+    pub tenant_scoped_endpoint: bool,
+    /// The web interface url.
+    pub webui_url: Option<String>,
+    /// The maximum number of builds returned per page, used to cap the builds queries.
+    ///
+    /// Upstream zuul does not report it: the cap only applies when a server sets this field.
+    pub max_page_size: Option<u32>,
+}
+
+/// The info endpoint envelope.
+#[derive(Deserialize)]
+struct InfoResponse {
+    info: ZuulInfo,
+}
+
+/// A Tenant.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tenant {
+    /// The tenant name.
+    pub name: String,
+    /// The number of projects.
+    pub projects: u32,
+    /// The number of queued items.
+    pub queue: u32,
+}
+
+/// A Project.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Project {
+    /// The project name.
+    pub name: String,
+    /// The project connection name.
+    pub connection_name: String,
+    /// The project type, e.g. `config` or `untrusted`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The project canonical name.
+    pub canonical_name: String,
+}
+
+/// A Project with its configuration.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectDetail {
+    /// The project name.
+    pub name: String,
+    /// The project canonical name.
+    pub canonical_name: String,
+    /// The project connection name.
+    pub connection_name: String,
+    /// The project type, e.g. `config` or `untrusted`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The project configurations, one per branch and template.
+    #[serde(default)]
+    pub configs: Vec<ProjectConfig>,
+}
+
+/// A Project configuration.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectConfig {
+    /// The project default branch.
+    pub default_branch: Option<String>,
+    /// The project merge mode, e.g. `merge-resolve`.
+    pub merge_mode: Option<String>,
+    /// The applied templates.
+    #[serde(default)]
+    pub templates: Vec<String>,
+    /// The pipelines configuration.
+    #[serde(default)]
+    pub pipelines: Vec<ProjectPipelineConfig>,
+}
+
+/// A Project pipeline configuration.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectPipelineConfig {
+    /// The pipeline name.
+    pub name: String,
+    /// The shared queue name.
+    pub queue_name: Option<String>,
+    /// The jobs definition.
+    #[serde(default)]
+    pub jobs: Vec<serde_json::Value>,
+}
+
+/// A Job definition.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    /// The job name.
+    pub name: String,
+    /// The job description.
+    pub description: Option<String>,
+    /// The job tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A Job definition with its variants.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobDetail {
+    /// The job name.
+    pub name: String,
+    /// The job description.
+    pub description: Option<String>,
+    /// The job variants.
+    pub variants: Vec<JobVariant>,
+}
+
+/// A Job variant.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobVariant {
+    /// The parent job name.
+    pub parent: Option<String>,
+    /// The branches matched by the variant.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// The nodeset name.
+    #[serde(default, with = "nodeset_name")]
+    pub nodeset: Option<String>,
+}
+
+/// A Pipeline definition.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pipeline {
+    /// The pipeline name.
+    pub name: String,
+    /// The pipeline manager, e.g. `independent` or `dependent`.
+    #[serde(default)]
+    pub manager: String,
+    /// The pipeline triggers configuration.
+    #[serde(rename = "triggers", default)]
+    pub trigger: Vec<serde_json::Value>,
+}
+
+/// A Job frozen for a project branch.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FrozenJob {
+    /// The job name.
+    pub name: String,
+    /// The names of the jobs it depends on.
+    #[serde(default, with = "dependency_names")]
+    pub dependencies: Vec<String>,
+}
+
+/// A Node.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Node {
+    /// The node id.
+    pub id: String,
+    /// The node labels.
+    #[serde(rename = "type", default)]
+    pub type_: Vec<String>,
+    /// The node provider connection name.
+    #[serde(default)]
+    pub connection_name: String,
+    /// The node state, e.g. `ready`, `in-use` or `hold`.
+    pub state: String,
+    /// The build using the node.
+    pub build: Option<String>,
+    /// The worker name.
+    pub worker_name: Option<String>,
+    /// The hold request id.
+    pub hold_request: Option<String>,
+}
+
+/// A Label that jobs can request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Label {
+    /// The label name.
+    pub name: String,
+}
+
+/// A Semaphore state.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SemaphoreInfo {
+    /// The semaphore name.
+    pub name: String,
+    /// Is the semaphore shared between tenants.
+    pub global: bool,
+    /// The maximum number of holders.
+    pub max: u32,
+    /// The current holders.
+    pub holders: SemaphoreHolders,
+}
+
+/// The Semaphore holders count.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SemaphoreHolders {
+    /// The total number of holders.
+    pub count: u32,
+    /// The number of holders in this tenant.
+    pub this_tenant: u32,
+    /// The number of holders in the other tenants.
+    pub other_tenants: u32,
+}
+
+/// A tenant Configuration error.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigError {
+    /// The location of the error.
+    pub source_context: SourceContext,
+    /// The error message.
+    pub error: String,
+}
+
+/// The location of a configuration object.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourceContext {
+    /// The project name.
+    pub project: String,
+    /// The branch name.
+    pub branch: String,
+    /// The file path.
+    pub path: String,
+}
+
+/// An Autohold request, to keep the nodes of a failed build for debugging.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AutoholdRequest {
+    /// The tenant name.
+    pub tenant: String,
+    /// The project name.
+    pub project: String,
+    /// The job name.
+    pub job: String,
+    /// The ref regex filter, e.g. `.*`.
+    #[serde(rename = "ref")]
+    pub ref_filter: String,
+    /// The reason of the hold.
+    pub reason: String,
+    /// The number of failed builds to hold.
+    pub count: u32,
+    /// How long the nodes are held, in second.
+    pub node_hold_expiration: Option<u64>,
+}
+
+/// An Autohold request status.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AutoholdInfo {
+    /// The request id.
+    pub id: String,
+    /// The tenant name.
+    pub tenant: String,
+    /// The project name.
+    pub project: String,
+    /// The job name.
+    pub job: String,
+    /// The ref regex filter.
+    pub ref_filter: String,
+    /// The reason of the hold.
+    pub reason: String,
+    /// The number of failed builds to hold.
+    pub max_count: u32,
+    /// The number of failed builds held.
+    #[serde(default)]
+    pub current_count: u32,
+    /// How long the nodes are held, in second.
+    pub node_expiration: Option<u64>,
+}
+
+/// A Promote request, to move changes to the head of a pipeline queue.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PromoteRequest {
+    /// The tenant name.
+    pub tenant: String,
+    /// The pipeline name.
+    pub pipeline: String,
+    /// The changes to promote, e.g. `42,1`.
+    pub changes: Vec<String>,
+}
+
+/// A Dequeue request, to remove a change from a pipeline queue.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DequeueRequest {
+    /// The tenant name.
+    pub tenant: String,
+    /// The pipeline name.
+    pub pipeline: String,
+    /// The project name.
+    pub project: String,
+    /// The change to dequeue, e.g. `42,1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<String>,
+    /// The ref to dequeue, e.g. `refs/heads/master`.
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub ref_: Option<String>,
+}
+
+/// An Enqueue request, to add a change to a pipeline.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EnqueueRequest {
+    /// The tenant name.
+    pub tenant: String,
+    /// The trigger connection name.
+    pub trigger: String,
+    /// The pipeline name.
+    pub pipeline: String,
+    /// The project name.
+    pub project: String,
+    /// The change to enqueue, e.g. `42,1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<String>,
+    /// The ref to enqueue, e.g. `refs/heads/master`.
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub ref_: Option<String>,
+    /// The ref old revision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldrev: Option<String>,
+    /// The ref new revision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newrev: Option<String>,
+}
+
+/// The live status of a tenant.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TenantStatus {
+    /// The pipelines status.
+    pub pipelines: Vec<PipelineStatus>,
+}
+
+/// The live status of a pipeline.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PipelineStatus {
+    /// The pipeline name.
+    pub name: String,
+    /// The pipeline manager, e.g. `independent` or `dependent`.
+    #[serde(default)]
+    pub manager: String,
+    /// The pipeline queues.
+    #[serde(default)]
+    pub change_queues: Vec<ChangeQueue>,
+}
+
+/// A pipeline change queue.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChangeQueue {
+    /// The queue name.
+    pub name: String,
+    /// The queue heads, each head being a list of dependent items.
+    #[serde(default)]
+    pub heads: Vec<Vec<QueueItem>>,
+}
+
+/// An item enqueued in a pipeline.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueueItem {
+    /// The change id, e.g. `42,1`, absent for ref updates.
+    pub id: Option<String>,
+    /// The project name.
+    pub project: String,
+    /// The enqueue time in milliseconds since the epoch.
+    pub enqueue_time: f64,
+    /// The item jobs.
+    #[serde(default)]
+    pub jobs: Vec<QueuedJob>,
+}
+
+/// A job of an enqueued item.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuedJob {
+    /// The job name.
+    pub name: String,
+    /// The build uuid, once the job started.
+    pub uuid: Option<String>,
+    /// The build result, once the job completed.
+    pub result: Option<String>,
+    /// Is the job voting.
+    #[serde(default)]
+    pub voting: bool,
+    /// The elapsed time in milliseconds.
+    pub elapsed_time: Option<u64>,
+    /// The estimated remaining time in milliseconds.
+    pub remaining_time: Option<u64>,
+    /// The live stream url.
+    pub url: Option<String>,
+}
+
+/// A Build result.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Build {
+    /// The build unique id.
+    pub uuid: String,
+    /// The job name.
+    pub job_name: String,
+    /// The job result.
+    pub result: BuildResult,
+    /// The start time.
+    #[serde(with = "python_utc_without_trailing_z")]
+    pub start_time: DateTime<Utc>,
+    /// The end time.
+    #[serde(with = "python_utc_without_trailing_z")]
+    pub end_time: DateTime<Utc>,
+    /// The job duration in second, see [Build::duration].
+    #[serde(rename = "duration", alias = "duration_secs", with = "rounded_float")]
+    pub duration_secs: u32,
+    /// The job voting status.
+    pub voting: bool,
+    /// The log url.
+    pub log_url: Option<String>,
+    /// The build artifacts.
+    pub artifacts: Vec<Artifact>,
+    /// The change's project name.
+    pub project: String,
+    /// The change's branch name.
+    pub branch: String,
+    /// The build pipeline.
+    pub pipeline: String,
+    /// The change (or PR) number.
+    pub change: Option<u64>,
+    /// The patchset number (or PR commit).
+    pub patchset: Option<String>,
+    /// The change ref.
+    #[serde(rename = "ref")]
+    pub change_ref: String,
+    /// The change url in the code review system.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_url: Option<String>,
+    /// The internal event id.
+    pub event_id: String,
+    /// The time the triggering event was received.
+    #[serde(default, with = "python_utc_without_trailing_z::option")]
+    pub event_timestamp: Option<DateTime<Utc>>,
+    /// The name of the node that ran the build.
+    pub node_name: Option<String>,
+    /// The executor that ran the build.
+    pub worker: Option<Worker>,
+    /// The uuid of the buildset the build belongs to, see [Zuul::get_buildset].
+    pub buildset_uuid: Option<String>,
+    /// The name of the change queue, e.g. `integrated`.
+    pub queue: Option<String>,
+    /// The artifacts provided by the job.
+    #[serde(default)]
+    pub provides: Vec<String>,
+    /// The artifacts required by the job.
+    #[serde(default)]
+    pub requires: Vec<String>,
+}
+
+impl Build {
+    /// The url of the zuul manifest artifact, listing the build logs.
+    pub fn zuul_manifest_url(&self) -> Option<&str> {
+        self.artifacts
+            .iter()
+            .find(|artifact| {
+                artifact
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.type_.as_deref())
+                    == Some("zuul_manifest")
+            })
+            .map(|artifact| artifact.url.as_str())
+    }
+
+    /// Check if the build succeeded.
+    #[inline]
+    pub fn is_successful(&self) -> bool {
+        self.result == BuildResult::Success
+    }
+
+    /// Check if the build failed.
+    #[inline]
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.result,
+            BuildResult::Failure
+                | BuildResult::PostFailure
+                | BuildResult::NodeFailure
+                | BuildResult::RetryLimit
+                | BuildResult::TimedOut
+        )
+    }
+
+    /// The job duration.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_secs as u64)
+    }
+
+    /// The time elapsed since the build ended.
+    pub fn age(&self) -> chrono::Duration {
+        Utc::now() - self.end_time
+    }
+
+    /// The log url, if it is present and valid.
+    pub fn log_url_parsed(&self) -> Option<Url> {
+        self.log_url.as_deref().and_then(|url| Url::parse(url).ok())
+    }
+}
+
+impl TryFrom<serde_json::Value> for Build {
+    type Error = ZuulError;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        decode(value)
+    }
+}
+
+/// Format a build on a single line: `{log_url} {uuid} {project} {job_name}`,
+/// where the log url is `N/A` when the build has no logs.
+impl fmt::Display for Build {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.log_url.as_deref().unwrap_or("N/A"),
+            self.uuid,
+            self.project,
+            self.job_name
+        )
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl Build {
+    /// A strategy generating valid builds, for property based tests.
+    ///
+    /// The uuids are hex strings, the start time is before the end time and
+    /// the duration is their difference.
+    pub fn arbitrary() -> impl proptest::strategy::Strategy<Value = Build> {
+        use proptest::prelude::*;
+        let ids = (
+            "[0-9a-f]{32}",
+            "[0-9a-f]{32}",
+            proptest::option::of("[0-9a-f]{32}"),
+        );
+        let job = (
+            "[a-z][a-z0-9-]{0,19}",
+            proptest::sample::select(BUILD_RESULTS.to_vec()),
+            any::<bool>(),
+            "[a-z]{1,10}/[a-z][a-z0-9-]{0,19}",
+            proptest::sample::select(vec!["master", "main", "stable/1.0"]),
+            proptest::sample::select(vec!["check", "gate", "post", "periodic"]),
+        );
+        let times = (1_420_070_400i64..1_893_456_000, 1u32..=86_400, 0i64..3_600);
+        let change = (
+            proptest::option::of(1u64..1_000_000),
+            1u32..30,
+            proptest::option::of("[a-z]{1,10}"),
+            proptest::option::of("[a-z]{1,10}-[0-9]{1,2}"),
+            proptest::collection::vec("[a-z][a-z0-9-]{0,19}", 0..3),
+            proptest::collection::vec("[a-z][a-z0-9-]{0,19}", 0..3),
+        );
+        (ids, job, times, change).prop_map(
+            |(
+                (uuid, event_id, buildset_uuid),
+                (job_name, result, voting, project, branch, pipeline),
+                (start, duration, event_delay),
+                (change, patchset, queue, node_name, provides, requires),
+            )| {
+                let start_time = DateTime::from_timestamp(start, 0).unwrap();
+                let change_ref = match change {
+                    Some(change) => {
+                        format!("refs/changes/{:02}/{}/{}", change % 100, change, patchset)
+                    }
+                    None => format!("refs/heads/{}", branch),
+                };
+                Build {
+                    log_url: Some(format!("https://logs.example.org/{}/", &uuid[..7])),
+                    uuid,
+                    job_name,
+                    result: result.parse().unwrap(),
+                    start_time,
+                    end_time: start_time + chrono::Duration::seconds(duration.into()),
+                    duration_secs: duration,
+                    voting,
+                    artifacts: Vec::new(),
+                    project,
+                    branch: branch.to_string(),
+                    pipeline: pipeline.to_string(),
+                    change,
+                    patchset: change.map(|_| patchset.to_string()),
+                    change_ref,
+                    ref_url: None,
+                    event_id,
+                    event_timestamp: Some(start_time - chrono::Duration::seconds(event_delay)),
+                    node_name,
+                    worker: None,
+                    buildset_uuid,
+                    queue,
+                    provides,
+                    requires,
+                }
+            },
+        )
+    }
+}
+
+/// Aggregate statistics of a set of builds, see [build_stats].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BuildStats {
+    /// The number of builds.
+    pub total: usize,
+    /// The number of successful builds.
+    pub successful: usize,
+    /// The number of failed builds.
+    pub failed: usize,
+    /// The mean duration in second.
+    pub mean_duration_secs: f64,
+    /// The median duration in second.
+    pub p50_duration_secs: u32,
+    /// The 95th percentile duration in second.
+    pub p95_duration_secs: u32,
+    /// The 99th percentile duration in second.
+    pub p99_duration_secs: u32,
+}
+
+/// Compute the statistics of a set of builds, the stats are zeroed when the slice is empty.
+pub fn build_stats(builds: &[Build]) -> BuildStats {
+    if builds.is_empty() {
+        return BuildStats::default();
+    }
+    let mut durations: Vec<u32> = builds.iter().map(|build| build.duration_secs).collect();
+    durations.sort_unstable();
+    // The nearest-rank percentile
+    let percentile = |p: usize| durations[(p * durations.len()).div_ceil(100) - 1];
+    BuildStats {
+        total: builds.len(),
+        successful: builds.iter().filter(|build| build.is_successful()).count(),
+        failed: builds.iter().filter(|build| build.is_failed()).count(),
+        mean_duration_secs: durations.iter().map(|&d| d as f64).sum::<f64>() / builds.len() as f64,
+        p50_duration_secs: percentile(50),
+        p95_duration_secs: percentile(95),
+        p99_duration_secs: percentile(99),
+    }
+}
+
+/// A Build result value.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum BuildResult {
+    /// The build succeeded.
+    Success,
+    /// The build failed.
+    Failure,
+    /// The build was skipped.
+    Skipped,
+    /// The build was aborted.
+    Aborted,
+    /// The build was retried too many times.
+    RetryLimit,
+    /// The build post-run playbook failed.
+    PostFailure,
+    /// The build could not get a node.
+    NodeFailure,
+    /// The build timed out.
+    TimedOut,
+    /// The build filled the executor disk.
+    DiskFull,
+    /// The change could not be merged.
+    MergerFailure,
+    /// The job configuration is invalid.
+    ConfigError,
+    /// The build was canceled.
+    Canceled,
+    /// Any other result.
+    Other(String),
+}
+
+impl BuildResult {
+    /// The result value as returned by the api.
+    pub fn as_str(&self) -> &str {
+        match self {
+            BuildResult::Success => "SUCCESS",
+            BuildResult::Failure => "FAILURE",
+            BuildResult::Skipped => "SKIPPED",
+            BuildResult::Aborted => "ABORTED",
+            BuildResult::RetryLimit => "RETRY_LIMIT",
+            BuildResult::PostFailure => "POST_FAILURE",
+            BuildResult::NodeFailure => "NODE_FAILURE",
+            BuildResult::TimedOut => "TIMED_OUT",
+            BuildResult::DiskFull => "DISK_FULL",
+            BuildResult::MergerFailure => "MERGER_FAILURE",
+            BuildResult::ConfigError => "CONFIG_ERROR",
+            BuildResult::Canceled => "CANCELED",
+            BuildResult::Other(s) => s,
+        }
+    }
+}
+
+impl From<&str> for BuildResult {
+    fn from(s: &str) -> Self {
+        match s {
+            "SUCCESS" => BuildResult::Success,
+            "FAILURE" => BuildResult::Failure,
+            "SKIPPED" => BuildResult::Skipped,
+            "ABORTED" => BuildResult::Aborted,
+            "RETRY_LIMIT" => BuildResult::RetryLimit,
+            "POST_FAILURE" => BuildResult::PostFailure,
+            "NODE_FAILURE" => BuildResult::NodeFailure,
+            "TIMED_OUT" => BuildResult::TimedOut,
+            "DISK_FULL" => BuildResult::DiskFull,
+            "MERGER_FAILURE" => BuildResult::MergerFailure,
+            "CONFIG_ERROR" => BuildResult::ConfigError,
+            "CANCELED" => BuildResult::Canceled,
+            s => BuildResult::Other(s.to_string()),
+        }
+    }
+}
+
+/// The known build result values.
+const BUILD_RESULTS: [&str; 12] = [
+    "SUCCESS",
+    "FAILURE",
+    "SKIPPED",
+    "ABORTED",
+    "RETRY_LIMIT",
+    "POST_FAILURE",
+    "NODE_FAILURE",
+    "TIMED_OUT",
+    "DISK_FULL",
+    "MERGER_FAILURE",
+    "CONFIG_ERROR",
+    "CANCELED",
+];
+
+/// The error returned when parsing an unknown build result.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnknownBuildResult(pub String);
+
+impl fmt::Display for UnknownBuildResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown build result {}, expected one of: {}",
+            self.0,
+            BUILD_RESULTS.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownBuildResult {}
+
+/// Parse a known build result, use [BuildResult::from] to accept any value.
+impl std::str::FromStr for BuildResult {
+    type Err = UnknownBuildResult;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match BuildResult::from(s) {
+            BuildResult::Other(s) => Err(UnknownBuildResult(s)),
+            result => Ok(result),
+        }
+    }
+}
+
+impl fmt::Display for BuildResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for BuildResult {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for BuildResult {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(BuildResult::from(s.as_str()))
+    }
+}
+
+/// A Buildset, the group of builds run for a change.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Buildset {
+    /// The buildset unique id.
+    pub uuid: String,
+    /// The buildset report message.
+    #[serde(default)]
+    pub message: String,
+    /// The buildset result.
+    pub result: Option<String>,
+    /// The change's project name.
+    pub project: String,
+    /// The change's branch name.
+    pub branch: String,
+    /// The buildset pipeline.
+    pub pipeline: String,
+    /// The change (or PR) number.
+    pub change: Option<u64>,
+    /// The patchset number (or PR commit).
+    pub patchset: Option<String>,
+    /// The buildset builds, only provided when querying a single buildset.
+    #[serde(default)]
+    pub builds: Vec<Build>,
+}
+
+impl TryFrom<serde_json::Value> for Buildset {
+    type Error = ZuulError;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        decode(value)
+    }
+}
+
+/// A Build executor.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Worker {
+    /// The executor name.
+    pub name: String,
+    /// The executor hostname.
+    pub hostname: String,
+}
+
+/// A Build artifact.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Artifact {
+    /// The artifact name.
+    pub name: String,
+    /// The artifact url.
+    pub url: String,
+    /// The artifact metadata.
+    pub metadata: Option<ArtifactMetadata>,
+}
+
+/// A Build artifact metadata.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ArtifactMetadata {
+    /// The artifact type, e.g. `zuul_manifest`.
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+}
+
+/// A Build logs manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Manifest {
+    /// The top level log entries.
+    pub tree: Vec<ManifestEntry>,
+}
+
+/// A Build logs manifest entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ManifestEntry {
+    /// The file or directory name.
+    pub name: String,
+    /// The file mimetype, `application/directory` for directories.
+    pub mimetype: String,
+    /// The directory entries.
+    pub children: Option<Vec<ManifestEntry>>,
+}
+
+/// A real-time event.
+#[derive(Debug, Clone)]
+pub enum ZuulEvent {
+    /// A build started.
+    BuildStarted(BuildStartedEvent),
+    /// A build completed.
+    BuildCompleted(BuildCompletedEvent),
+    /// A pipeline status changed.
+    PipelineChanged(serde_json::Value),
+    /// An event of an unknown type, with its type name.
+    Unknown(String),
+}
+
+/// A build started event.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct BuildStartedEvent {
+    /// The build unique id.
+    pub uuid: String,
+    /// The job name.
+    pub job_name: String,
+    /// The project name.
+    pub project: String,
+    /// The pipeline name.
+    pub pipeline: String,
+}
+
+/// A build completed event.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct BuildCompletedEvent {
+    /// The build unique id.
+    pub uuid: String,
+    /// The job name.
+    pub job_name: String,
+    /// The project name.
+    pub project: String,
+    /// The pipeline name.
+    pub pipeline: String,
+    /// The build result.
+    pub result: BuildResult,
+}
+
+// Copy pasta from https://serde.rs/custom-date-format.html
+mod python_utc_without_trailing_z {
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s = format!("{}", date.format(FORMAT));
+        serializer.serialize_str(&s)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        NaiveDateTime::parse_from_str(&s, FORMAT)
+            .map(|dt| dt.and_utc())
+            .map_err(serde::de::Error::custom)
+    }
+
+    // The same format for optional date
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{self, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S>(date: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match date {
+                Some(date) => super::serialize(date, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] DateTime<Utc>);
+            let v = Option::<Wrapper>::deserialize(deserializer)?;
+            Ok(v.map(|Wrapper(date)| date))
+        }
+    }
+}
+
+// For some reason, durations are sometime provided as f32, e.g. `42.0`
+mod rounded_float {
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(duration: &u32, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u32(*duration)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<u32, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = f32::deserialize(deserializer)?;
+        Ok(v as u32)
+    }
+}
+
+// The nodeset is either a name or a nodeset definition
+mod nodeset_name {
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(name: &Option<String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match name {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = serde_json::Value::deserialize(deserializer)?;
+        Ok(match v {
+            serde_json::Value::String(name) => Some(name),
+            v => v["name"].as_str().map(|name| name.to_string()),
+        })
+    }
+}
+
+// The dependencies are either names or objects with a name and a soft flag
+mod dependency_names {
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(names: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        names.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+        values
+            .into_iter()
+            .map(|v| match v {
+                serde_json::Value::String(name) => Ok(name),
+                v => v["name"]
+                    .as_str()
+                    .map(|name| name.to_string())
+                    .ok_or_else(|| serde::de::Error::custom("missing dependency name")),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use futures_util::pin_mut;
+    use futures_util::stream::StreamExt;
+
+    #[test]
+    fn it_parse_url() {
+        let assert_url =
+            |url, expected: &str| assert_eq!(parse_root_url(url).unwrap().to_string(), expected);
+        assert_url("https://example.com", "https://example.com/");
+        assert_url("https://example.com/", "https://example.com/");
+        assert_url("https://example.com/api", "https://example.com/api/");
+        assert_url("https://example.com/api/", "https://example.com/api/");
+
+        let api = parse_root_url("https://example.com/api").unwrap();
+        assert_eq!(encode_project_name("zuul"), "zuul");
+        assert_eq!(encode_project_name("openstack/nova"), "openstack%2Fnova");
+        assert_eq!(encode_project_name("org/my repo"), "org%2Fmy%20repo");
+        assert_eq!(encode_project_name("zuul-jobs_v2.0~"), "zuul-jobs_v2.0~");
+        assert_eq!(
+            api.join(&format!(
+                "project/{}",
+                encode_project_name("openstack/nova")
+            ))
+            .unwrap()
+            .to_string(),
+            "https://example.com/api/project/openstack%2Fnova"
+        );
+    }
+
+    pub(crate) fn make_build(uuid: &str, end_time: DateTime<Utc>) -> Build {
+        Build {
+            uuid: String::from(uuid),
+            job_name: "job".to_string(),
+            result: BuildResult::Success,
+            start_time: end_time + Duration::minutes(-42),
+            end_time,
+            duration_secs: 42,
+            voting: true,
+            log_url: Some("http://localhost/".to_string() + &String::from(uuid)),
+            artifacts: [].to_vec(),
+            project: "project".to_string(),
+            branch: "main".to_string(),
+            pipeline: "check".to_string(),
+            change: Some(42),
+            patchset: None,
+            change_ref: "head".to_string(),
+            ref_url: None,
+            event_id: "uuid".to_string(),
+            event_timestamp: Some(end_time + Duration::minutes(-50)),
+            node_name: Some("centos-8".to_string()),
+            worker: None,
+            buildset_uuid: None,
+            queue: None,
+            provides: Vec::new(),
+            requires: Vec::new(),
+        }
+    }
+
+    /// Helper function to drop milli second from a DateTime so that the json encoding round trip
+    pub(crate) fn drop_milli(dt: DateTime<Utc>) -> DateTime<Utc> {
+        DateTime::from_timestamp(dt.timestamp(), 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_stream_builds() {
+        env_logger::init();
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+
+        let now = drop_milli(Utc::now());
+        let b0 = make_build("42", now);
+        let b1 = make_build("build1", now);
+        let b2 = make_build("build2", now);
+        let b3 = make_build("build3", now);
+        // Simulate a sliding page
+        let page1 = serde_json::json!([b1.clone(), b2.clone()].to_vec());
+        let page2 = serde_json::json!([b2.clone(), b3.clone()].to_vec());
+
+        let m0 = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("limit", "1");
+            then.status(200).json_body(serde_json::json!([b0]));
+        });
+        let m1 = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200).json_body(page1);
+        });
+        let m2 = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "2");
+            then.status(200).json_body(page2);
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let mut got = Vec::new();
+        let s = client.builds_tail(std::time::Duration::from_millis(50), None);
+        pin_mut!(s); // needed for iteration
+        while let Some(build) = s.next().await {
+            println!("got {:?}", build);
+            got.push(build.unwrap());
+            if got.len() >= 3 {
+                break;
+            }
+        }
+        m0.assert();
+        m1.assert();
+        m2.assert();
+        assert_eq!(got, [b1, b2, b3].to_vec());
+    }
+
+    #[tokio::test]
+    async fn it_stream_filtered_builds() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let server = MockServer::start();
+        let mock_page = |skip: &str, builds: Vec<Build>| {
+            server.mock(|when, then| {
+                when.method(GET)
+                    .path("/builds")
+                    .query_param("project", "zuul/zuul")
+                    .query_param("limit", "2")
+                    .query_param("skip", skip);
+                then.status(200).json_body(serde_json::json!(builds));
+            })
+        };
+        let m1 = mock_page("0", [b1.clone(), b2.clone()].to_vec());
+        let m2 = mock_page("2", [b3.clone()].to_vec());
+        let m3 = mock_page("3", Vec::new());
+
+        let client = create_client(&server.url("/")).unwrap();
+        let mut query = BuildsQuery::default();
+        query.project("zuul/zuul").limit(2);
+        let got: Vec<Build> = client.builds_stream_filtered(&query).collect().await;
+        m1.assert();
+        m2.assert();
+        m3.assert();
+        assert_eq!(got, [b1, b2, b3]);
+    }
+
+    #[tokio::test]
+    async fn it_tail_builds_where() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let b0 = make_build("build0", now);
+        let b1 = make_build("build1", now);
+        let mut b2 = make_build("build2", now);
+        b2.result = BuildResult::Failure;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b2.clone(), b1.clone(), b0.clone()]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let s = client.builds_tail_where(
+            std::time::Duration::from_millis(50),
+            Some("build0".to_string()),
+            |build| build.result == BuildResult::Failure,
+        );
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap().unwrap(), b2);
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn it_stream_builds_since_time() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let b1 = make_build("build1", now);
+        let b2 = make_build("build2", now + Duration::hours(-1));
+        let b3 = make_build("build3", now + Duration::hours(-3));
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b1.clone(), b2.clone(), b3.clone()]));
+        });
+        let next = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "3");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let got: Vec<Build> = client
+            .builds_stream_since_time(now + Duration::hours(-2))
+            .collect()
+            .await;
+        m.assert();
+        next.assert_hits(0);
+        assert_eq!(got, [b1, b2]);
+    }
+
+    #[tokio::test]
+    async fn it_fetch_all_builds() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (0..5)
+            .map(|idx| make_build(&format!("build{}", idx), now))
+            .collect();
+        let server = MockServer::start();
+        let mock_page = |skip: usize, limit: usize| {
+            let page = builds[skip..(skip + limit).min(builds.len())].to_vec();
+            server.mock(move |when, then| {
+                when.method(GET)
+                    .path("/builds")
+                    .query_param("skip", skip.to_string())
+                    .query_param("limit", limit.to_string());
+                then.status(200).json_body(serde_json::json!(page));
+            })
+        };
+        let pages = [
+            mock_page(0, 2),
+            mock_page(2, 2),
+            mock_page(4, 2),
+            mock_page(5, 2),
+        ];
+        let capped = mock_page(2, 1);
+
+        let client = create_client(&server.url("/")).unwrap();
+        let got = client
+            .fetch_all_builds(BuildsQuery::default().limit(2))
+            .await
+            .unwrap();
+        assert_eq!(got, builds);
+        pages.iter().for_each(|m| m.assert());
+
+        let got = client
+            .fetch_all_builds(BuildsQuery::default().limit(2).max_results(3))
+            .await
+            .unwrap();
+        assert_eq!(got, builds[..3]);
+        capped.assert();
+
+        let server = MockServer::start();
+        let oversized = server.mock(|when, then| {
+            when.method(GET)
+                .path("/builds")
+                .query_param("skip", "0")
+                .query_param("limit", "1");
+            then.status(200).json_body(serde_json::json!(builds[..2]));
+        });
+        let client = create_client(&server.url("/")).unwrap();
+        let got = client
+            .fetch_all_builds(BuildsQuery::default().limit(2).max_results(1))
+            .await
+            .unwrap();
+        assert_eq!(got, builds[..1]);
+        oversized.assert();
+    }
+
+    #[tokio::test]
+    async fn it_tail_builds_error() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("limit", "1");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let s = client.builds_tail(std::time::Duration::from_millis(50), None);
+        pin_mut!(s);
+        assert!(matches!(s.next().await, Some(Err(ZuulError::NotFound))));
+        assert!(s.next().await.is_none());
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn it_maps_error_status() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/denied/tenants");
+            then.status(401);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/broken/tenants");
+            then.status(503);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/invalid/builds");
+            then.status(200)
+                .json_body(serde_json::json!([{"uuid": "build1"}]));
+        });
+
+        let client = create_client(&server.url("/denied/")).unwrap();
+        assert!(matches!(
+            client.get_tenants().await,
+            Err(ZuulError::Unauthorized)
+        ));
+        let client = create_client(&server.url("/broken/")).unwrap();
+        assert!(matches!(
+            client.get_tenants().await,
+            Err(ZuulError::ServerError(503))
+        ));
+        let client = create_client(&server.url("/invalid/")).unwrap();
+        match client.builds_unsafe().await {
+            Err(ZuulError::Decode { uuid, .. }) => assert_eq!(uuid, "build1"),
+            v => panic!("Unexpected result {:?}", v),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_get_builds() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let builds = [
+            make_build("build1", now),
+            make_build("build2", now + Duration::hours(-1)),
+        ];
+
+        // Start a lightweight mock server.
+        let server = MockServer::start();
+
+        // Create a mock on the server.
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/builds")
+                .query_param("complete", "true");
+            then.status(200)
+                .header("content-type", "application/json")
+                .json_body(serde_json::json!(builds.to_vec()));
+        });
+
+        // Get builds
+        let client = create_client(&server.url("/")).unwrap();
+        let got = client.builds_unsafe().await.unwrap();
+        m.assert();
+        assert_eq!(got, builds);
+    }
+
+    #[tokio::test]
+    async fn it_get_tenants() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([
+                {"name": "openstack", "projects": 42, "queue": 3},
+                {"name": "zuul", "projects": 12, "queue": 0}
+            ]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_tenants().await.unwrap();
+        m.assert();
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].name, "openstack");
+        assert_eq!(got[0].projects, 42);
+        assert_eq!(got[1].queue, 0);
+    }
+
+    #[tokio::test]
+    async fn it_authenticate_requests() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenants")
+                .header("authorization", "Bearer secret");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api"))
+            .with_auth_token("secret")
+            .build()
+            .unwrap();
+        client.get_tenants().await.unwrap();
+        m.assert();
+        assert!(matches!(
+            ZuulClientBuilder::new("https://zuul")
+                .with_auth_token("bad\ntoken")
+                .build(),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_authenticate_basic_requests() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenants")
+                .header("authorization", "Basic dXNlcjpwYXNz");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api"))
+            .with_basic_auth("user", "pass")
+            .build()
+            .unwrap();
+        client.get_tenants().await.unwrap();
+        m.assert();
+        match ZuulClientBuilder::new("https://zuul")
+            .with_basic_auth("user", "pass")
+            .with_auth_token("secret")
+            .build()
+        {
+            Err(ZuulError::InvalidConfig(e)) => {
+                assert_eq!(e, "cannot set both basic auth and bearer token")
+            }
+            _ => panic!("Expected an invalid config error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_timeout_requests() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200)
+                .delay(std::time::Duration::from_millis(500))
+                .json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api"))
+            .with_connect_timeout(std::time::Duration::from_secs(1))
+            .with_request_timeout(std::time::Duration::from_millis(50))
+            .build()
+            .unwrap();
+        match client.get_tenants().await {
+            Err(ZuulError::Http(e)) => assert!(e.is_timeout()),
+            v => panic!("Unexpected result {:?}", v),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_use_proxy() {
+        use httpmock::prelude::*;
+        let proxy = MockServer::start();
+        let m = proxy.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new("http://zuul.example.org/api")
+            .with_proxy(&proxy.base_url())
+            .unwrap()
+            .with_no_proxy(&["localhost"])
+            .build()
+            .unwrap();
+        client.get_tenants().await.unwrap();
+        m.assert();
+        assert!(matches!(
+            ZuulClientBuilder::new("https://zuul").with_proxy("not a url"),
+            Err(ZuulError::InvalidUrl(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_retry_requests() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/pipelines");
+            then.status(500);
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api"))
+            .with_retry_strategy(RetryStrategy {
+                initial_delay_ms: 1,
+                max_delay_secs: 0,
+                max_attempts: 2,
+            })
+            .build()
+            .unwrap();
+        assert!(matches!(
+            client.get_pipelines("local").await,
+            Err(ZuulError::ServerError(500))
+        ));
+        m.assert_hits(3);
+    }
+
+    const TEST_CA_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBjDCCATKgAwIBAgITIMBhzU5UnkuxhxbHXL6VDpdQfjAKBggqhkjOPQQDAjAb
+MRkwFwYDVQQDDBB6dXVsLmV4YW1wbGUub3JnMCAXDTI2MTAxNjE2NDAwM1oYDzIx
+MjYwOTIyMTY0MDAzWjAbMRkwFwYDVQQDDBB6dXVsLmV4YW1wbGUub3JnMFkwEwYH
+KoZIzj0CAQYIKoZIzj0DAQcDQgAE2xzDtCmpRBuzfEb8tC7y25G/J5w2lrEywKQG
+cY5F6b3vK2dkwVQTDQo2HvBiEhca6z7SosOCyg+qhLCBn2gTpaNTMFEwHQYDVR0O
+BBYEFCtASE2YILQ00QHiMfkHmI1+UeqAMB8GA1UdIwQYMBaAFCtASE2YILQ00QHi
+MfkHmI1+UeqAMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIge+98
+kgN4vLYa2XsoIVb49hUh9l4OREQOhkIdqchqS9cCIQCEdmA3RFh/4vIRG1Mvp/PY
+2L72R2E51ngSWYoi1NH47Q==
+-----END CERTIFICATE-----";
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn it_build_with_tls_options() {
+        // The mock server only speaks http: this checks that the builder
+        // accepts the tls settings, not the https handshake itself.
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api"))
+            .with_tls_ca_cert(TEST_CA_CERT.as_bytes())
+            .unwrap()
+            .with_danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        client.get_tenants().await.unwrap();
+        m.assert();
+        assert!(matches!(
+            ZuulClientBuilder::new("https://zuul").with_tls_ca_cert(b"not a cert"),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_scope_tenant() {
+        use httpmock::prelude::*;
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let server = MockServer::start();
+        let projects = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/projects");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+        let builds = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/builds")
+                .query_param("skip", "0");
+            then.status(200).json_body(serde_json::json!([build]));
+        });
+        let tenants = server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap().tenant("local");
+        assert_eq!(client.name(), "local");
+        assert!(client.get_projects().await.unwrap().is_empty());
+        assert!(client.builds(0, 1).await.unwrap()[0].is_ok());
+        let s = client.builds_stream();
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap(), build);
+        client.get_tenants().await.unwrap();
+        projects.assert();
+        builds.assert_hits(2);
+        tenants.assert();
+    }
+
+    #[tokio::test]
+    async fn it_encode_tenant_name() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let projects = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/my%2Ftenant/projects");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+        let builds = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/my%2Ftenant/builds");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/api/"))
+            .unwrap()
+            .tenant("my/tenant");
+        assert!(client.get_projects().await.unwrap().is_empty());
+        assert!(client.builds(0, 1).await.unwrap().is_empty());
+        projects.assert();
+        builds.assert();
+    }
+
+    #[tokio::test]
+    async fn it_get_info() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/info");
+            then.status(200).json_body(serde_json::json!({"info": {
+                "zuul_version": "4.10.0",
+                "webui_url": "https://zuul.example.org/",
+                "capabilities": {"job_history": true}
+            }}));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_info().await.unwrap();
+        m.assert();
+        assert_eq!(got.zuul_version, "4.10.0");
+        assert_eq!(got.max_dependencies_count, None);
+        assert!(!got.tenant_scoped_endpoint);
+    }
+
+    #[tokio::test]
+    async fn it_get_projects() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/projects");
+            then.status(200).json_body(serde_json::json!([{
+                "name": "zuul/zuul",
+                "connection_name": "opendev",
+                "type": "untrusted",
+                "canonical_name": "opendev.org/zuul/zuul"
+            }]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_projects("local").await.unwrap();
+        m.assert();
+        assert_eq!(got[0].type_, "untrusted");
+        assert_eq!(got[0].canonical_name, "opendev.org/zuul/zuul");
+    }
+
+    #[tokio::test]
+    async fn it_get_jobs() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/jobs");
+            then.status(200).json_body(serde_json::json!([
+                {"name": "base", "description": "The base job", "tags": ["ci"]},
+                {"name": "linters"},
+                {"description": "A job without a name"}
+            ]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_jobs("local").await.unwrap();
+        m.assert();
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].tags, ["ci"]);
+        assert_eq!(got[1].description, None);
+    }
+
+    #[tokio::test]
+    async fn it_get_pipelines() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/pipelines");
+            then.status(200).json_body(serde_json::json!([{
+                "name": "check",
+                "manager": "independent",
+                "triggers": [{"name": "gerrit", "driver": "gerrit"}]
+            }]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_pipelines("local").await.unwrap();
+        m.assert();
+        assert_eq!(got[0].name, "check");
+        assert_eq!(got[0].manager, "independent");
+        assert_eq!(got[0].trigger[0]["driver"], "gerrit");
+    }
+
+    #[tokio::test]
+    async fn it_get_build() {
+        use httpmock::prelude::*;
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/build/build1");
+            then.status(200).json_body(serde_json::json!(build));
+        });
+        let m404 = server.mock(|when, then| {
+            when.method(GET).path("/build/unknown");
+            then.status(404);
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        assert_eq!(client.get_build("build1").await.unwrap(), build);
+        assert!(matches!(
+            client.get_build("unknown").await,
+            Err(ZuulError::NotFound)
+        ));
+        m.assert();
+        m404.assert();
+    }
+
+    #[tokio::test]
+    async fn it_query_builds() {
+        use httpmock::prelude::*;
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/builds")
+                .query_param("complete", "true")
+                .query_param("project", "zuul/zuul")
+                .query_param("result", "FAILURE")
+                .query_param("change", "42")
+                .query_param("skip", "0")
+                .query_param("limit", "10");
+            then.status(200).json_body(serde_json::json!([build]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let got = BuildsQuery::default()
+            .project("zuul/zuul")
+            .result("FAILURE")
+            .change(42)
+            .limit(10)
+            .fetch(&client)
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got, [build]);
+    }
+
+    #[tokio::test]
+    async fn it_get_buildsets() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/buildsets")
+                .query_param("skip", "0")
+                .query_param("limit", "1");
+            then.status(200).json_body(serde_json::json!([{
+                "uuid": "a1b2",
+                "result": "SUCCESS",
+                "message": "Build succeeded (gate pipeline).",
+                "project": "zuul/zuul",
+                "branch": "master",
+                "pipeline": "gate",
+                "change": 42,
+                "patchset": "1",
+                "ref": "refs/changes/42/42/1",
+                "event_id": "ev"
+            }]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_buildsets("local", 0, 1).await.unwrap();
+        m.assert();
+        assert_eq!(got[0].uuid, "a1b2");
+        assert_eq!(got[0].result.as_deref(), Some("SUCCESS"));
+        assert!(got[0].builds.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_get_buildset() {
+        use httpmock::prelude::*;
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/buildset/a1b2");
+            then.status(200).json_body(serde_json::json!({
+                "uuid": "a1b2",
+                "result": "SUCCESS",
+                "message": "Build succeeded (check pipeline).",
+                "project": "zuul/zuul",
+                "branch": "master",
+                "pipeline": "check",
+                "change": 42,
+                "patchset": "1",
+                "builds": [build]
+            }));
+        });
+        let m404 = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/buildset/unknown");
+            then.status(404);
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_buildset("local", "a1b2").await.unwrap();
+        assert_eq!(got.builds, [build]);
+        assert!(matches!(
+            client.get_buildset("local", "unknown").await,
+            Err(ZuulError::NotFound)
+        ));
+        m.assert();
+        m404.assert();
+    }
+
+    #[test]
+    fn it_decodes_build() {
+        let data = r#"
+            {
+              "uuid": "5bae5607ae964331bb5878aec0777637",
+              "job_name": "hlint",
+              "result": "SUCCESS",
+              "start_time": "2021-10-13T12:57:20",
+              "end_time": "2021-10-13T12:58:42",
+              "duration": 82.0,
+              "voting": true,
+              "log_url": "https://softwarefactory-project.io/logs/94/22894/1/gate/hlint/5bae560/",
+              "artifacts": [
+                {
+                  "name": "Zuul Manifest",
+                  "url": "https://softwarefactory-project.io/logs/94/22894/1/gate/hlint/5bae560/zuul-manifest.json",
+                  "metadata": {
+                    "type": "zuul_manifest"
+                  }
+                },
+                {
+                  "name": "HLint report",
+                  "url": "https://softwarefactory-project.io/logs/94/22894/1/gate/hlint/5bae560/hlint.html"
+                }
+              ],
+              "project": "software-factory/matrix-client-haskell",
+              "branch": "master",
+              "pipeline": "gate",
+              "change": 22894,
+              "patchset": "1",
+              "ref": "refs/changes/94/22894/1",
+              "ref_url": "https://softwarefactory-project.io/r/22894",
+              "event_id": "40d9b63d749c48eabb3d7918cfab0d31",
+              "event_timestamp": "2021-10-13T12:50:01",
+              "node_name": "fedora-34",
+              "worker": {"name": "ze01", "hostname": "ze01.example.org"},
+              "buildset_uuid": "0b3bd4bd0eb74ffb8e5d1a3c1f4bb6e1",
+              "queue": "integrated",
+              "provides": ["matrix-client-image"],
+              "requires": ["ghc-image"]
+            }"#;
+        let build: Build = serde_json::from_str(data).unwrap();
+        assert_eq!(build.uuid, "5bae5607ae964331bb5878aec0777637");
+        assert_eq!(build.result, BuildResult::Success);
+        assert_eq!(
+            build.ref_url.as_deref(),
+            Some("https://softwarefactory-project.io/r/22894")
+        );
+        assert_eq!(build.node_name.as_deref(), Some("fedora-34"));
+        assert_eq!(build.worker.as_ref().unwrap().hostname, "ze01.example.org");
+        assert_eq!(
+            build.buildset_uuid.as_deref(),
+            Some("0b3bd4bd0eb74ffb8e5d1a3c1f4bb6e1")
+        );
+        assert_eq!(build.queue.as_deref(), Some("integrated"));
+        assert_eq!(build.provides, ["matrix-client-image"]);
+        assert_eq!(build.requires, ["ghc-image"]);
+        let metadata = build.artifacts[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata.type_.as_deref(), Some("zuul_manifest"));
+        assert_eq!(build.artifacts[1].metadata, None);
+        assert_eq!(
+            build.event_timestamp.map(|ts| ts.to_string()),
+            Some("2021-10-13 12:50:01 UTC".to_string())
+        );
+        let value = serde_json::to_value(&build).unwrap();
+        assert_eq!(
+            value["ref_url"],
+            "https://softwarefactory-project.io/r/22894"
+        );
+    }
+
+    #[test]
+    fn it_decodes_build_result() {
+        let decode = |s: &str| serde_json::from_value::<BuildResult>(serde_json::json!(s)).unwrap();
+        assert_eq!(decode("RETRY_LIMIT"), BuildResult::RetryLimit);
+        assert_eq!(
+            decode("NEW_RESULT"),
+            BuildResult::Other("NEW_RESULT".to_string())
+        );
+        assert_eq!(
+            serde_json::json!(BuildResult::PostFailure),
+            serde_json::json!("POST_FAILURE")
+        );
+        assert_eq!(
+            serde_json::json!(BuildResult::Other("NEW_RESULT".to_string())),
+            serde_json::json!("NEW_RESULT")
+        );
+    }
+
+    #[tokio::test]
+    async fn it_get_nodes() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/nodes");
+            then.status(200).json_body(serde_json::json!([{
+                "id": "0000000042",
+                "type": ["centos-8"],
+                "connection_name": "nodepool",
+                "state": "hold",
+                "hold_request": "0000000001"
+            }]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.tenant("local").get_nodes().await.unwrap();
+        m.assert();
+        assert_eq!(got[0].type_, ["centos-8"]);
+        assert_eq!(got[0].state, "hold");
+        assert_eq!(got[0].build, None);
+        assert_eq!(got[0].hold_request.as_deref(), Some("0000000001"));
+    }
+
+    #[tokio::test]
+    async fn it_get_labels() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/labels");
+            then.status(200)
+                .json_body(serde_json::json!([{"name": "centos-8"}, {"name": "fedora"}]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_labels("local").await.unwrap();
+        m.assert();
+        let names: Vec<&str> = got.iter().map(|label| label.name.as_str()).collect();
+        assert_eq!(names, ["centos-8", "fedora"]);
+    }
+
+    #[tokio::test]
+    async fn it_get_semaphores() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/semaphores");
+            then.status(200).json_body(serde_json::json!([{
+                "name": "deploy",
+                "global": false,
+                "max": 1,
+                "holders": {"count": 1, "this_tenant": 1, "other_tenants": 0}
+            }]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_semaphores("local").await.unwrap();
+        m.assert();
+        assert_eq!(got[0].name, "deploy");
+        assert_eq!(got[0].holders.count, got[0].max);
+    }
+
+    #[tokio::test]
+    async fn it_get_config_errors() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/config-errors");
+            then.status(200).json_body(serde_json::json!([{
+                "source_context": {
+                    "project": "zuul/zuul",
+                    "branch": "master",
+                    "path": ".zuul.yaml"
+                },
+                "error": "Job base not defined"
+            }]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_config_errors("local").await.unwrap();
+        m.assert();
+        assert_eq!(got[0].source_context.path, ".zuul.yaml");
+        assert_eq!(got[0].error, "Job base not defined");
+    }
+
+    #[tokio::test]
+    async fn it_manage_autoholds() {
+        use httpmock::prelude::*;
+        let info = serde_json::json!({
+            "id": "0000000001",
+            "tenant": "local",
+            "project": "zuul/zuul",
+            "job": "tox",
+            "ref_filter": ".*",
+            "reason": "debug",
+            "max_count": 1,
+            "current_count": 0,
+            "node_expiration": 86400
+        });
+        let server = MockServer::start();
+        let create = server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/tenant/local/project/zuul%2Fzuul/autohold")
+                .header("authorization", "Bearer secret")
+                .json_body(serde_json::json!({
+                    "tenant": "local",
+                    "project": "zuul/zuul",
+                    "job": "tox",
+                    "ref": ".*",
+                    "reason": "debug",
+                    "count": 1,
+                    "node_hold_expiration": null
+                }));
+            then.status(200).json_body(info.clone());
+        });
+        let list = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/autohold");
+            then.status(200).json_body(serde_json::json!([info]));
+        });
+        let get = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/autohold/0000000001");
+            then.status(200).json_body(info.clone());
+        });
+        let delete = server.mock(|when, then| {
+            when.method(DELETE)
+                .path("/api/tenant/local/autohold/0000000001");
+            then.status(204);
+        });
+
+        let req = AutoholdRequest {
+            tenant: "local".to_string(),
+            project: "zuul/zuul".to_string(),
+            job: "tox".to_string(),
+            ref_filter: ".*".to_string(),
+            reason: "debug".to_string(),
+            count: 1,
+            node_hold_expiration: None,
+        };
+        let anonymous = create_client(&server.url("/api/")).unwrap();
+        assert!(matches!(
+            anonymous.create_autohold("local", &req).await,
+            Err(ZuulError::Unauthorized)
+        ));
+
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_auth_token("secret")
+            .build()
+            .unwrap()
+            .tenant("local");
+        let got = client.create_autohold(&req).await.unwrap();
+        assert_eq!(got.id, "0000000001");
+        assert_eq!(client.list_autoholds().await.unwrap()[0].max_count, 1);
+        assert_eq!(
+            client
+                .get_autohold("0000000001")
+                .await
+                .unwrap()
+                .node_expiration,
+            Some(86400)
+        );
+        client.delete_autohold("0000000001").await.unwrap();
+        create.assert();
+        list.assert();
+        get.assert();
+        delete.assert();
+    }
+
+    #[tokio::test]
+    async fn it_promote_change() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/tenant/local/promote")
+                .header("authorization", "Bearer secret")
+                .json_body(serde_json::json!({
+                    "tenant": "local",
+                    "pipeline": "gate",
+                    "changes": ["42,1"]
+                }));
+            then.status(204);
+        });
+
+        let req = PromoteRequest {
+            tenant: "local".to_string(),
+            pipeline: "gate".to_string(),
+            changes: vec!["42,1".to_string()],
+        };
+        let anonymous = create_client(&server.url("/api/")).unwrap();
+        assert!(matches!(
+            anonymous.promote_change("local", &req).await,
+            Err(ZuulError::Unauthorized)
+        ));
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_auth_token("secret")
+            .build()
+            .unwrap();
+        client.promote_change("local", &req).await.unwrap();
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn it_dequeue_change() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/tenant/local/project/zuul%2Fzuul/dequeue")
+                .json_body(serde_json::json!({
+                    "tenant": "local",
+                    "pipeline": "check",
+                    "project": "zuul/zuul",
+                    "change": "42,1"
+                }));
+            then.status(200);
+        });
+
+        let mut req = DequeueRequest {
+            tenant: "local".to_string(),
+            pipeline: "check".to_string(),
+            project: "zuul/zuul".to_string(),
+            change: Some("42,1".to_string()),
+            ref_: None,
+        };
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_auth_token("secret")
+            .build()
+            .unwrap();
+        client.dequeue_change("local", &req).await.unwrap();
+        m.assert();
+
+        req.ref_ = Some("refs/heads/master".to_string());
+        assert!(matches!(
+            client.dequeue_change("local", &req).await,
+            Err(ZuulError::InvalidConfig(_))
+        ));
+        req.change = None;
+        req.ref_ = None;
+        assert!(matches!(
+            client.dequeue_change("local", &req).await,
+            Err(ZuulError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_enqueue_change() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/tenant/local/project/zuul%2Fzuul/enqueue")
+                .json_body(serde_json::json!({
+                    "tenant": "local",
+                    "trigger": "gerrit",
+                    "pipeline": "post",
+                    "project": "zuul/zuul",
+                    "ref": "refs/heads/master",
+                    "oldrev": "0000000000000000000000000000000000000000",
+                    "newrev": "b3a5f7d2c1e0b3a5f7d2c1e0b3a5f7d2c1e0b3a5"
+                }));
+            then.status(200);
+        });
+
+        let req = EnqueueRequest {
+            tenant: "local".to_string(),
+            trigger: "gerrit".to_string(),
+            pipeline: "post".to_string(),
+            project: "zuul/zuul".to_string(),
+            change: None,
+            ref_: Some("refs/heads/master".to_string()),
+            oldrev: Some("0000000000000000000000000000000000000000".to_string()),
+            newrev: Some("b3a5f7d2c1e0b3a5f7d2c1e0b3a5f7d2c1e0b3a5".to_string()),
+        };
+        let anonymous = create_client(&server.url("/api/")).unwrap();
+        assert!(matches!(
+            anonymous.enqueue_change("local", &req).await,
+            Err(ZuulError::Unauthorized)
+        ));
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_auth_token("secret")
+            .build()
+            .unwrap();
+        client.enqueue_change("local", &req).await.unwrap();
+        m.assert();
+    }
+
+    #[test]
+    fn it_skips_missing_ref_url() {
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let value = serde_json::to_value(&build).unwrap();
+        assert!(value.get("ref_url").is_none());
+    }
+
+    #[test]
+    fn it_defaults_build_dependencies() {
+        let mut value = serde_json::to_value(make_build("build1", drop_milli(Utc::now()))).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        fields.remove("provides");
+        fields.remove("requires");
+        let build: Build = serde_json::from_value(value).unwrap();
+        assert!(build.provides.is_empty());
+        assert!(build.requires.is_empty());
+    }
+
+    #[test]
+    fn it_finds_zuul_manifest() {
+        let mut build = make_build("build1", drop_milli(Utc::now()));
+        assert_eq!(build.zuul_manifest_url(), None);
+        build.artifacts = vec![
+            Artifact {
+                name: "report".to_string(),
+                url: "http://localhost/report.html".to_string(),
+                metadata: None,
+            },
+            Artifact {
+                name: "Zuul Manifest".to_string(),
+                url: "http://localhost/zuul-manifest.json".to_string(),
+                metadata: Some(ArtifactMetadata {
+                    type_: Some("zuul_manifest".to_string()),
+                }),
+            },
+        ];
+        assert_eq!(
+            build.zuul_manifest_url(),
+            Some("http://localhost/zuul-manifest.json")
+        );
+    }
+
+    #[test]
+    fn it_checks_build_result() {
+        let mut build = make_build("build1", drop_milli(Utc::now()));
+        let check = |build: &mut Build, result: BuildResult| {
+            build.result = result;
+            (build.is_successful(), build.is_failed())
+        };
+        assert_eq!(check(&mut build, BuildResult::Success), (true, false));
+        for result in [
+            BuildResult::Failure,
+            BuildResult::PostFailure,
+            BuildResult::NodeFailure,
+            BuildResult::RetryLimit,
+            BuildResult::TimedOut,
+        ] {
+            assert_eq!(check(&mut build, result), (false, true));
+        }
+        for result in [
+            BuildResult::Skipped,
+            BuildResult::Aborted,
+            BuildResult::DiskFull,
+            BuildResult::MergerFailure,
+            BuildResult::ConfigError,
+            BuildResult::Canceled,
+            BuildResult::Other("LOST".to_string()),
+        ] {
+            assert_eq!(check(&mut build, result), (false, false));
+        }
+    }
+
+    #[test]
+    fn it_converts_duration() {
+        let build = make_build("build1", drop_milli(Utc::now()));
+        assert_eq!(build.duration(), std::time::Duration::from_secs(42));
+        assert_eq!(serde_json::to_value(&build).unwrap()["duration"], 42);
+
+        let mut value = serde_json::to_value(&build).unwrap();
+        let duration = value.as_object_mut().unwrap().remove("duration").unwrap();
+        value["duration_secs"] = duration;
+        assert_eq!(serde_json::from_value::<Build>(value).unwrap(), build);
+    }
+
+    #[test]
+    fn it_computes_age() {
+        let build = make_build("build1", Utc::now() + Duration::hours(-1));
+        let age = build.age();
+        assert!(age >= Duration::hours(1));
+        assert!(age < Duration::hours(1) + Duration::minutes(1));
+    }
+
+    #[test]
+    fn it_parses_log_url() {
+        let mut build = make_build("build1", drop_milli(Utc::now()));
+        let url = build.log_url_parsed().unwrap();
+        assert_eq!(
+            url.join("job-output.txt").unwrap().path(),
+            "/job-output.txt"
+        );
+        build.log_url = Some("not a url".to_string());
+        assert_eq!(build.log_url_parsed(), None);
+        build.log_url = None;
+        assert_eq!(build.log_url_parsed(), None);
+    }
+
+    #[tokio::test]
+    async fn it_get_status() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/status");
+            then.status(200).json_body(serde_json::json!({
+                "zuul_version": "4.10.0",
+                "pipelines": [{"name": "check", "change_queues": []}]
+            }));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client.get_status("local").await.unwrap();
+        m.assert();
+        assert_eq!(got["pipelines"][0]["name"], "check");
+    }
+
+    #[tokio::test]
+    async fn it_get_tenant_status() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/status");
+            then.status(200).json_body(serde_json::json!({
+                "zuul_version": "4.10.0",
+                "pipelines": [{
+                    "name": "gate",
+                    "manager": "dependent",
+                    "change_queues": [{
+                        "name": "integrated",
+                        "heads": [[{
+                            "id": "42,1",
+                            "project": "zuul/zuul",
+                            "enqueue_time": 1620000000000u64,
+                            "jobs": [
+                                {"name": "tox-py38", "uuid": "1234", "result": null,
+                                 "voting": true, "elapsed_time": 1000, "remaining_time": 2000,
+                                 "url": "stream/1234"},
+                                {"name": "tox-docs", "uuid": null, "result": null,
+                                 "voting": false, "elapsed_time": null, "remaining_time": null,
+                                 "url": null}
+                            ]
+                        }]]
+                    }]
+                }]
+            }));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let status = client.get_tenant_status("local").await.unwrap();
+        m.assert();
+        let pipeline = &status.pipelines[0];
+        assert_eq!(pipeline.manager, "dependent");
+        let item = &pipeline.change_queues[0].heads[0][0];
+        assert_eq!(item.id.as_deref(), Some("42,1"));
+        assert_eq!(item.jobs.len(), 2);
+        assert_eq!(item.jobs[0].uuid.as_deref(), Some("1234"));
+        assert!(!item.jobs[1].voting);
+    }
+
+    #[tokio::test]
+    async fn it_wait_for_build() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let mut running =
+            serde_json::to_value(make_build("build1", drop_milli(Utc::now()))).unwrap();
+        running["result"] = serde_json::Value::Null;
+        running["end_time"] = serde_json::Value::Null;
+        let mut m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/build/build1");
+            then.status(200).json_body(running);
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let poll = std::time::Duration::from_millis(10);
+        let got = client
+            .wait_for_build(
+                "local",
+                "build1",
+                poll,
+                std::time::Duration::from_millis(100),
+            )
+            .await;
+        assert!(matches!(got, Err(ZuulError::Timeout)));
+        assert!(m.hits() > 1);
+        m.delete();
+
+        let build = make_build("build1", drop_milli(Utc::now()));
+        m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/build/build1");
+            then.status(200).json_body_obj(&build);
+        });
+        let got = client
+            .wait_for_build("local", "build1", poll, std::time::Duration::from_secs(5))
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got, build);
+    }
+
+    #[tokio::test]
+    async fn it_wait_for_buildset() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let done = serde_json::to_value(make_build("build1", drop_milli(Utc::now()))).unwrap();
+        let mut running =
+            serde_json::to_value(make_build("build2", drop_milli(Utc::now()))).unwrap();
+        running["result"] = serde_json::Value::Null;
+        running["end_time"] = serde_json::Value::Null;
+        let buildset = |builds| {
+            serde_json::json!({
+                "uuid": "bs1", "result": null, "project": "zuul/zuul", "branch": "master",
+                "pipeline": "check", "change": 42, "patchset": "1", "builds": builds
+            })
+        };
+        let mut m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/buildset/bs1");
+            then.status(200)
+                .json_body(buildset(serde_json::json!([done.clone(), running])));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let poll = std::time::Duration::from_millis(10);
+        let got = client
+            .wait_for_buildset("local", "bs1", poll, std::time::Duration::from_millis(100))
+            .await;
+        assert!(matches!(got, Err(ZuulError::Timeout)));
+        m.delete();
+
+        let mut done2 = serde_json::to_value(make_build("build2", drop_milli(Utc::now()))).unwrap();
+        done2["result"] = serde_json::json!("FAILURE");
+        m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/buildset/bs1");
+            then.status(200)
+                .json_body(buildset(serde_json::json!([done, done2])));
+        });
+        let got = client
+            .wait_for_buildset("local", "bs1", poll, std::time::Duration::from_secs(5))
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got.builds.len(), 2);
+        assert_eq!(got.builds[1].result, BuildResult::Failure);
+    }
+
+    #[tokio::test]
+    async fn it_get_build_console_output() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/logs/build1/job-output.txt");
+            then.status(200).body("PLAY [all]\n");
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let mut build = make_build("build1", drop_milli(Utc::now()));
+        build.log_url = Some(server.url("/logs/build1/"));
+        let got = client.get_build_console_output(&build).await.unwrap();
+        m.assert();
+        assert_eq!(got, "PLAY [all]\n");
+
+        build.log_url = None;
+        let got = client.get_build_console_output(&build).await;
+        assert!(matches!(got, Err(ZuulError::NotFound)));
+
+        build.log_url = Some("http://127.0.0.1:1/logs/".to_string());
+        let got = client.get_build_console_output(&build).await;
+        assert!(matches!(got, Err(ZuulError::Http(_))));
+    }
+
+    #[tokio::test]
+    async fn it_fetch_manifest() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/logs/build1/zuul-manifest.json");
+            then.status(200).json_body(serde_json::json!({
+                "tree": [
+                    {"name": "job-output.txt", "mimetype": "text/plain", "size": 42},
+                    {"name": "zuul-info", "mimetype": "application/directory", "children": [
+                        {"name": "inventory.yaml", "mimetype": "text/plain"}
+                    ]}
+                ],
+                "index_links": false
+            }));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let mut build = make_build("build1", drop_milli(Utc::now()));
+        let got = client.fetch_manifest(&build).await;
+        assert!(matches!(got, Err(ZuulError::NotFound)));
+
+        build.artifacts = vec![Artifact {
+            name: "Zuul Manifest".to_string(),
+            url: server.url("/logs/build1/zuul-manifest.json"),
+            metadata: Some(ArtifactMetadata {
+                type_: Some("zuul_manifest".to_string()),
+            }),
+        }];
+        let manifest = client.fetch_manifest(&build).await.unwrap();
+        m.assert();
+        assert_eq!(manifest.tree.len(), 2);
+        assert_eq!(manifest.tree[0].children, None);
+        let children = manifest.tree[1].children.as_ref().unwrap();
+        assert_eq!(children[0].name, "inventory.yaml");
+    }
+
+    #[tokio::test]
+    async fn it_download_artifact() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/logs/build1/report.tar.gz");
+            then.status(200).body(vec![0u8, 1, 2, 3]);
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let artifact = Artifact {
+            name: "report".to_string(),
+            url: server.url("/logs/build1/report.tar.gz"),
+            metadata: None,
+        };
+        let got = client.download_artifact(&artifact).await.unwrap();
+        assert_eq!(got.as_ref(), &[0, 1, 2, 3]);
+
+        let s = client.download_artifact_stream(&artifact);
+        pin_mut!(s);
+        let mut got = Vec::new();
+        while let Some(chunk) = s.next().await {
+            got.extend_from_slice(&chunk.unwrap());
+        }
+        m.assert_hits(2);
+        assert_eq!(got, vec![0, 1, 2, 3]);
+
+        let missing = Artifact {
+            url: server.url("/logs/build1/missing"),
+            ..artifact.clone()
+        };
+        let s = client.download_artifact_stream(&missing);
+        pin_mut!(s);
+        assert!(matches!(s.next().await, Some(Err(ZuulError::NotFound))));
+        assert!(s.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn it_encrypt_secret() {
+        use base64::Engine;
+        use httpmock::prelude::*;
+        use rsa::pkcs8::{EncodePublicKey, LineEnding};
+        let private_key = rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 1024).unwrap();
+        let pem = private_key
+            .to_public_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/key/zuul%2Fzuul.pub");
+            then.status(200).body(&pem);
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client
+            .get_project_public_key("local", "zuul/zuul")
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got, pem);
+
+        let secret = "s3cr3t".repeat(20);
+        let encrypted = encrypt_secret(&got, secret.as_bytes()).unwrap();
+        let chunks: Vec<&str> = encrypted.lines().collect();
+        assert_eq!(chunks.len(), 2);
+        let decrypted: Vec<u8> = chunks
+            .iter()
+            .flat_map(|chunk| {
+                let ciphertext = base64::engine::general_purpose::STANDARD
+                    .decode(chunk)
+                    .unwrap();
+                private_key
+                    .decrypt(rsa::Oaep::new::<sha1::Sha1>(), &ciphertext)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(decrypted, secret.as_bytes());
+
+        assert!(matches!(
+            encrypt_secret("not a key", b"secret"),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+        let small_key = rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 256)
+            .unwrap()
+            .to_public_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        assert!(matches!(
+            encrypt_secret(&small_key, b"secret"),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_events_stream() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/events")
+                .header("accept", "text/event-stream");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body(concat!(
+                    "event: build-started\n",
+                    "data: {\"uuid\": \"b1\", \"job_name\": \"linters\", ",
+                    "\"project\": \"zuul/zuul\", \"pipeline\": \"check\"}\n\n",
+                    "event: build-completed\n",
+                    "data: {\"uuid\": \"b1\", \"job_name\": \"linters\", ",
+                    "\"project\": \"zuul/zuul\", \"pipeline\": \"check\", ",
+                    "\"result\": \"SUCCESS\"}\n\n",
+                    "event: pipeline-changed\n",
+                    "data: {\"name\": \"check\"}\n\n",
+                    "event: tenant-reconfigured\n",
+                    "data: {}\n\n",
+                    "event: build-started\n",
+                    "data: {}\n\n",
+                ));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let s = client.events_stream("local");
+        pin_mut!(s);
+        match s.next().await {
+            Some(Ok(ZuulEvent::BuildStarted(event))) => assert_eq!(event.uuid, "b1"),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        match s.next().await {
+            Some(Ok(ZuulEvent::BuildCompleted(event))) => {
+                assert_eq!(event.result, BuildResult::Success)
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        match s.next().await {
+            Some(Ok(ZuulEvent::PipelineChanged(value))) => assert_eq!(value["name"], "check"),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        match s.next().await {
+            Some(Ok(ZuulEvent::Unknown(name))) => assert_eq!(name, "tenant-reconfigured"),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        assert!(matches!(
+            s.next().await,
+            Some(Err(ZuulError::Decode { .. }))
+        ));
+        assert!(s.next().await.is_none());
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn it_merge_pool_builds() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let (server1, server2) = (MockServer::start(), MockServer::start());
+        let m1 = server1.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b1.clone(), b2.clone()]));
+        });
+        let m2 = server2.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b2.clone(), b3.clone()]));
+        });
+
+        let pool = ZuulPool::new(&[&server1.url("/api/"), &server2.url("/api/")]).unwrap();
+        let mut got: Vec<Build> = pool.builds_stream_merged().take(3).collect().await;
+        m1.assert();
+        m2.assert();
+        got.sort();
+        assert_eq!(got, vec![b1, b2, b3]);
+
+        assert!(matches!(
+            ZuulPool::new(&[&server1.url("/api/"), "not an url"]),
+            Err(ZuulError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn it_creates_client_from_env() {
+        std::env::remove_var("ZUUL_API_URL");
+        assert!(matches!(
+            Zuul::from_env(),
+            Err(ZuulError::InvalidConfig(e)) if e == "ZUUL_API_URL not set"
+        ));
+
+        std::env::set_var("ZUUL_API_URL", "https://zuul.example.org/api");
+        std::env::set_var("ZUUL_AUTH_TOKEN", "secret");
+        let client = Zuul::from_env().unwrap();
+        assert_eq!(client.api.as_str(), "https://zuul.example.org/api/");
+        assert!(client.authenticated);
+
+        let path = std::env::temp_dir().join("zuul-rs-test-ca.pem");
+        std::fs::write(&path, TEST_CA_CERT).unwrap();
+        std::env::set_var("ZUUL_CA_CERT_PATH", &path);
+        assert!(Zuul::from_env().is_ok());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(Zuul::from_env(), Err(ZuulError::InvalidConfig(_))));
+
+        std::env::remove_var("ZUUL_API_URL");
+        std::env::remove_var("ZUUL_AUTH_TOKEN");
+        std::env::remove_var("ZUUL_CA_CERT_PATH");
+    }
+
+    #[test]
+    fn it_reads_config_file() {
+        let path = std::env::temp_dir().join("zuul-rs-test-client.toml");
+        std::fs::write(
+            &path,
+            "url = \"https://zuul.example.org/api/\"\ntoken = \"secret\"\n",
+        )
+        .unwrap();
+        let config = ZuulConfig::from_file(&path).unwrap();
+        assert_eq!(
+            config,
+            ZuulConfig {
+                url: "https://zuul.example.org/api/".to_string(),
+                token: Some("secret".to_string()),
+                tenant: None,
+            }
+        );
+
+        std::fs::write(&path, "token = \"secret\"\n").unwrap();
+        assert!(matches!(
+            ZuulConfig::from_file(&path),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            ZuulConfig::from_file(&path),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_cancel_builds_stream() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let server = MockServer::start();
+        let m1 = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b1.clone(), b2.clone()]));
+        });
+        let m2 = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "2");
+            then.status(200).json_body(serde_json::json!([b3.clone()]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let token = CancellationToken::new();
+        let s = client.builds_stream_cancellable(token.clone());
+        pin_mut!(s);
+        assert_eq!(s.next().await, Some(b1));
+        token.cancel();
+        // The current page is still yielded
+        assert_eq!(s.next().await, Some(b2));
+        assert_eq!(s.next().await, None);
+        m1.assert();
+        m2.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn it_tail_builds_with_checkpoint() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let path = std::env::temp_dir().join("zuul-rs-test-checkpoint.json");
+        BuildsCheckpoint {
+            last_uuid: b1.uuid.clone(),
+            timestamp: now,
+        }
+        .save_to_file(&path)
+        .unwrap();
+
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b3.clone(), b2.clone(), b1.clone()]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let s = client.builds_tail_with_checkpoint(std::time::Duration::from_millis(10), &path);
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap().unwrap(), b3);
+        assert_eq!(s.next().await.unwrap().unwrap(), b2);
+        // The next batch is empty, the checkpoint is saved
+        tokio::time::timeout(std::time::Duration::from_millis(100), s.next())
+            .await
+            .unwrap_err();
+        assert!(m.hits() >= 2);
+        let checkpoint = BuildsCheckpoint::load_from_file(&path).unwrap();
+        assert_eq!(checkpoint.last_uuid, b3.uuid);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            BuildsCheckpoint::load_from_file(&path),
+            Err(ZuulError::InvalidConfig(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_feeds_builds_to_channel() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b3.clone(), b2.clone(), b1.clone()]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let (handle, mut rx) =
+            client.builds_to_channel(std::time::Duration::from_millis(10), Some(b1.uuid), 1);
+        assert_eq!(rx.recv().await, Some(b3));
+        assert_eq!(rx.recv().await, Some(b2));
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn it_counts_metrics() {
+        use httpmock::prelude::*;
+        use std::sync::atomic::Ordering;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let server = MockServer::start();
+        let mock_page = |skip: &str, builds: Vec<Build>| {
+            server.mock(|when, then| {
+                when.method(GET)
+                    .path("/api/builds")
+                    .query_param("skip", skip);
+                then.status(200).json_body(serde_json::json!(builds));
+            })
+        };
+        mock_page("0", vec![b1.clone(), b2.clone()]);
+        mock_page("2", vec![b2.clone(), b3.clone()]);
+        mock_page("4", vec![]);
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let metrics = client.metrics();
+        let got: Vec<Build> = client
+            .builds_stream_filtered(&BuildsQuery::default())
+            .collect()
+            .await;
+        assert_eq!(got, vec![b1, b2, b3]);
+        assert_eq!(metrics.builds_fetched.load(Ordering::Relaxed), 4);
+        assert_eq!(metrics.builds_deduplicated.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.api_errors.load(Ordering::Relaxed), 0);
+
+        let client = ZuulClientBuilder::new(&server.url("/missing/"))
+            .with_retry_strategy(RetryStrategy {
+                initial_delay_ms: 1,
+                max_delay_secs: 1,
+                max_attempts: 2,
+            })
+            .build()
+            .unwrap();
+        let got: Vec<Build> = client.tenant("local").builds_stream().collect().await;
+        assert!(got.is_empty());
+        let metrics = client.metrics();
+        assert_eq!(metrics.retries.load(Ordering::Relaxed), 2);
+        assert_eq!(metrics.api_errors.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn it_computes_build_stats() {
+        assert_eq!(build_stats(&[]), BuildStats::default());
+
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (1..=100)
+            .map(|idx| {
+                let mut build = make_build(&idx.to_string(), now);
+                build.duration_secs = idx;
+                if idx % 10 == 0 {
+                    build.result = BuildResult::Failure;
+                }
+                build
+            })
+            .collect();
+        let stats = build_stats(&builds);
+        assert_eq!(stats.total, 100);
+        assert_eq!(stats.successful, 90);
+        assert_eq!(stats.failed, 10);
+        assert_eq!(stats.mean_duration_secs, 50.5);
+        assert_eq!(stats.p50_duration_secs, 50);
+        assert_eq!(stats.p95_duration_secs, 95);
+        assert_eq!(stats.p99_duration_secs, 99);
+
+        let stats = build_stats(&builds[..1]);
+        assert_eq!(stats.p50_duration_secs, 1);
+        assert_eq!(stats.p99_duration_secs, 1);
+    }
+
+    #[test]
+    fn it_displays_build() {
+        let mut build = make_build("build1", drop_milli(Utc::now()));
+        assert_eq!(
+            build.to_string(),
+            "http://localhost/build1 build1 project job"
+        );
+        build.log_url = None;
+        assert_eq!(build.to_string(), "N/A build1 project job");
+    }
+
+    #[tokio::test]
+    async fn it_stream_tenant_builds_since_time() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let b1 = make_build("build1", now);
+        let b2 = make_build("build2", now + Duration::hours(-3));
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/builds")
+                .query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b1.clone(), b2.clone()]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got: Vec<Build> = client
+            .tenant("local")
+            .builds_stream_since_time(now + Duration::hours(-2))
+            .collect()
+            .await;
+        m.assert();
+        assert_eq!(got, [b1]);
+    }
+
+    #[test]
+    fn it_parses_build_result() {
+        for result in BUILD_RESULTS {
+            assert_eq!(result.parse::<BuildResult>().unwrap().as_str(), result);
+        }
+        assert_eq!("FAILURE".parse(), Ok(BuildResult::Failure));
+        let err = "failure".parse::<BuildResult>().unwrap_err();
+        assert_eq!(err, UnknownBuildResult("failure".to_string()));
+        assert!(err.to_string().starts_with(
+            "unknown build result failure, expected one of: SUCCESS, FAILURE, SKIPPED"
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_get_builds_page() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2) = (make_build("build1", now), make_build("build2", now));
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("skip", "2")
+                .query_param("limit", "2");
+            then.status(200)
+                .header("X-Total-Count", "42")
+                .json_body(serde_json::json!([b1.clone(), b2.clone(), {"uuid": "bad"}]));
+        });
+        let without_count = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("skip", "0");
+            then.status(200).json_body(serde_json::json!([b1.clone()]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let page = client
+            .get_builds_page(BuildsQuery::default().skip(2).limit(2))
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(
+            page,
+            Page {
+                items: vec![b1.clone(), b2],
+                total_count: Some(42)
+            }
+        );
+
+        let page = client
+            .get_builds_page(&BuildsQuery::default())
+            .await
+            .unwrap();
+        without_count.assert();
+        assert_eq!(page.items, vec![b1]);
+        assert_eq!(page.total_count, None);
+    }
+
+    #[tokio::test]
+    async fn it_stream_finite_builds() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let server = MockServer::start();
+        let page1: Vec<Build> = (0..50)
+            .map(|idx| make_build(&idx.to_string(), now))
+            .collect();
+        let page2 = vec![make_build("last", now)];
+        let m1 = server.mock(|when, then| {
+            when.method(GET)
+                .path("/builds")
+                .query_param("skip", "0")
+                .query_param("limit", "50");
+            then.status(200).json_body(serde_json::json!(page1));
+        });
+        let m2 = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "50");
+            then.status(200).json_body(serde_json::json!(page2));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let got: Vec<Build> = client.builds_stream_finite().collect().await;
+        m1.assert();
+        m2.assert();
+        assert_eq!(got.len(), 51);
+        assert_eq!(got[50].uuid, "last");
+    }
+
+    #[test]
+    fn it_bounds_the_dedup_window() {
+        let mut window = DedupWindow::new(2);
+        window.insert("build1".to_string());
+        window.insert("build2".to_string());
+        window.insert("build2".to_string());
+        assert!(window.contains("build1"));
+        window.insert("build3".to_string());
+        assert!(!window.contains("build1"));
+        assert!(window.contains("build2"));
+        assert!(window.contains("build3"));
+        assert_eq!(window.order.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn it_stream_builds_with_dedup_window() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
+            make_build("build1", now),
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b1.clone(), b2.clone()]));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "2");
+            then.status(200)
+                .json_body(serde_json::json!([b2.clone(), b1.clone(), b3.clone()]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        // With a window of 1, build1 is forgotten when build2 is seen.
+        let got: Vec<Build> = client
+            .builds_stream_with_dedup_window(1)
+            .take(4)
+            .collect()
+            .await;
+        assert_eq!(got, vec![b1.clone(), b2, b1, b3]);
+    }
+
+    #[tokio::test]
+    async fn it_stream_builds_with_page_size() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/builds")
+                .query_param("skip", "0")
+                .query_param("limit", "100");
+            then.status(200)
+                .json_body(serde_json::json!([make_build("build1", now)]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let s = client.builds_stream_with_page_size(100);
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap().uuid, "build1");
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn it_cap_stream_page_size() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let server = MockServer::start();
+        let info = server.mock(|when, then| {
+            when.method(GET).path("/info");
+            then.status(200)
+                .json_body(serde_json::json!({"info": {"max_page_size": 30}}));
+        });
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/builds")
+                .query_param("skip", "0")
+                .query_param("limit", "30");
+            then.status(200)
+                .json_body(serde_json::json!([make_build("build1", now)]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let s = client.builds_stream_with_page_size(100);
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap().uuid, "build1");
+        let got: Vec<Build> = client.builds_stream_finite().collect().await;
+        assert_eq!(got.len(), 1);
+        // The server maximum is only queried once
+        info.assert();
+        m.assert_hits(2);
+    }
+
+    #[tokio::test]
+    async fn it_stream_builds_max_pages() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2) = (make_build("build1", now), make_build("build2", now));
+        let server = MockServer::start();
         let m1 = server.mock(|when, then| {
             when.method(GET).path("/builds").query_param("skip", "0");
-            then.status(200).json_body(page1);
+            then.status(200).json_body(serde_json::json!([b1.clone()]));
         });
         let m2 = server.mock(|when, then| {
-            when.method(GET).path("/builds").query_param("skip", "2");
-            then.status(200).json_body(page2);
+            when.method(GET).path("/builds").query_param("skip", "1");
+            then.status(200).json_body(serde_json::json!([b2.clone()]));
         });
 
         let client = create_client(&server.url("/")).unwrap();
-        let mut got = Vec::new();
-        let s = client.builds_tail(std::time::Duration::from_millis(50), None);
-        pin_mut!(s); // needed for iteration
-        while let Some(build) = s.next().await {
-            println!("got {:?}", build);
-            got.push(build);
-            if got.len() >= 3 {
-                break;
-            }
-        }
-        m0.assert();
+        let got: Vec<Build> = client.builds_stream_max_pages(2).collect().await;
         m1.assert();
         m2.assert();
-        assert_eq!(got, [b1, b2, b3].to_vec());
+        assert_eq!(got, vec![b1, b2]);
+
+        let got: Vec<Build> = client.builds_stream_max_pages(0).collect().await;
+        assert!(got.is_empty());
+        m1.assert();
     }
 
     #[tokio::test]
-    async fn it_get_builds() {
+    async fn it_tail_builds_without_blocking() {
         use httpmock::prelude::*;
         let now = drop_milli(Utc::now());
-        let builds = [
+        let (b1, b2) = (make_build("build1", now), make_build("build2", now));
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200)
+                .json_body(serde_json::json!([b2.clone(), b1.clone()]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let s = client.builds_tail(std::time::Duration::from_secs(10), Some(b1.uuid));
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap().unwrap(), b2);
+        // The stream is now sleeping, this must not block the runtime
+        let start = std::time::Instant::now();
+        tokio::time::timeout(std::time::Duration::from_millis(100), s.next())
+            .await
+            .unwrap_err();
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn it_tail_builds_with_error_strategy() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let (b1, b2, b3) = (
             make_build("build1", now),
-            make_build("build2", now + Duration::hours(-1)),
-        ];
+            make_build("build2", now),
+            make_build("build3", now),
+        );
+        let server = MockServer::start();
+        let failed = server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(500);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "50");
+            then.status(200)
+                .json_body(serde_json::json!([b3.clone(), b2.clone(), b1.clone()]));
+        });
+        let client = create_client(&server.url("/")).unwrap();
+        let delay = std::time::Duration::from_secs(10);
 
-        // Start a lightweight mock server.
+        // Skip moves to the next page
+        let s = client.builds_tail_with_error_strategy(
+            delay,
+            Some(b1.uuid.clone()),
+            ErrorStrategy::Skip,
+        );
+        pin_mut!(s);
+        assert_eq!(s.next().await.unwrap().unwrap(), b3);
+        assert_eq!(s.next().await.unwrap().unwrap(), b2);
+        failed.assert_hits(1);
+
+        // Terminate ends the stream without retrying
+        let s = client.builds_tail_with_error_strategy(
+            delay,
+            Some(b1.uuid.clone()),
+            ErrorStrategy::Terminate,
+        );
+        pin_mut!(s);
+        assert!(matches!(
+            s.next().await,
+            Some(Err(ZuulError::ServerError(500)))
+        ));
+        assert!(s.next().await.is_none());
+        failed.assert_hits(2);
+
+        // Retry backs off before giving up
+        let retry = RetryStrategy {
+            initial_delay_ms: 1,
+            max_delay_secs: 0,
+            max_attempts: 2,
+        };
+        let s = client.builds_tail_with_error_strategy(
+            delay,
+            Some(b1.uuid),
+            ErrorStrategy::Retry(retry),
+        );
+        pin_mut!(s);
+        assert!(matches!(
+            s.next().await,
+            Some(Err(ZuulError::ServerError(500)))
+        ));
+        assert!(s.next().await.is_none());
+        failed.assert_hits(5);
+    }
+
+    #[tokio::test]
+    async fn it_get_builds_for_change() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (0..3)
+            .map(|idx| make_build(&format!("build{}", idx), now))
+            .collect();
         let server = MockServer::start();
+        let page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/builds")
+                .query_param("change", "12345")
+                .query_param("patchset", "2")
+                .query_param("skip", "0");
+            then.status(200).json_body(serde_json::json!(builds));
+        });
+        let last = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/builds")
+                .query_param("change", "12345")
+                .query_param("skip", "3");
+            then.status(200).json_body(serde_json::json!([]));
+        });
 
-        // Create a mock on the server.
-        let m = server.mock(|when, then| {
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client
+            .tenant("local")
+            .get_builds_for_change(12345, Some("2"))
+            .await
+            .unwrap();
+        assert_eq!(got, builds);
+        page.assert();
+        last.assert();
+    }
+
+    #[tokio::test]
+    async fn it_get_latest_build_for_job() {
+        use httpmock::prelude::*;
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let server = MockServer::start();
+        let found = server.mock(|when, then| {
             when.method(GET)
-                .path("/builds")
-                .query_param("complete", "true");
+                .path("/api/tenant/local/builds")
+                .query_param("job_name", "linters")
+                .query_param("branch", "main")
+                .query_param("skip", "0")
+                .query_param("limit", "1");
             then.status(200)
-                .header("content-type", "application/json")
-                .json_body(serde_json::json!(builds.to_vec()));
+                .json_body(serde_json::json!([build.clone()]));
+        });
+        let missing = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/builds")
+                .query_param("job_name", "unknown");
+            then.status(200).json_body(serde_json::json!([]));
         });
 
-        // Get builds
-        let client = create_client(&server.url("/")).unwrap();
-        let got = client.builds_unsafe().await.unwrap();
-        m.assert();
-        assert_eq!(got, builds);
+        let client = create_client(&server.url("/api/")).unwrap();
+        let tenant = client.tenant("local");
+        assert_eq!(
+            tenant
+                .get_latest_build_for_job("linters", "main")
+                .await
+                .unwrap(),
+            Some(build)
+        );
+        assert_eq!(
+            tenant
+                .get_latest_build_for_job("unknown", "main")
+                .await
+                .unwrap(),
+            None
+        );
+        found.assert();
+        missing.assert();
     }
 
-    #[test]
-    fn it_decodes_build() {
-        let data = r#"
-            {
-              "uuid": "5bae5607ae964331bb5878aec0777637",
-              "job_name": "hlint",
-              "result": "SUCCESS",
-              "start_time": "2021-10-13T12:57:20",
-              "end_time": "2021-10-13T12:58:42",
-              "duration": 82.0,
-              "voting": true,
-              "log_url": "https://softwarefactory-project.io/logs/94/22894/1/gate/hlint/5bae560/",
-              "artifacts": [
+    #[tokio::test]
+    async fn it_get_last_build_for_job_by_result() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let success = Build {
+            result: BuildResult::Success,
+            ..make_build("build1", now)
+        };
+        let failure = Build {
+            result: BuildResult::Failure,
+            ..make_build("build2", now)
+        };
+        let server = MockServer::start();
+        let mock_result = |result: &'static str, build: &Build| {
+            let build = build.clone();
+            server.mock(move |when, then| {
+                when.method(GET)
+                    .path("/api/tenant/local/builds")
+                    .query_param("job_name", "linters")
+                    .query_param("branch", "main")
+                    .query_param("result", result)
+                    .query_param("limit", "1");
+                then.status(200).json_body(serde_json::json!([build]));
+            })
+        };
+        let mocks = [
+            mock_result("SUCCESS", &success),
+            mock_result("FAILURE", &failure),
+        ];
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let tenant = client.tenant("local");
+        assert_eq!(
+            tenant
+                .last_successful_build_for_job("linters", "main")
+                .await
+                .unwrap(),
+            Some(success)
+        );
+        assert_eq!(
+            tenant
+                .last_failed_build_for_job("linters", "main")
+                .await
+                .unwrap(),
+            Some(failure)
+        );
+        mocks.iter().for_each(|m| m.assert());
+    }
+
+    #[tokio::test]
+    async fn it_count_builds() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (0..3)
+            .map(|idx| make_build(&format!("build{}", idx), now))
+            .collect();
+        let server = MockServer::start();
+        let counted = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("project", "counted")
+                .query_param("limit", "1");
+            then.status(200)
+                .header("X-Total-Count", "1234")
+                .json_body(serde_json::json!([builds[0].clone()]));
+        });
+        let count_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("project", "fetched")
+                .query_param("limit", "1");
+            then.status(200)
+                .json_body(serde_json::json!([builds[0].clone()]));
+        });
+        let all_pages = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("project", "fetched")
+                .query_param("skip", "0")
+                .query_param("limit", "50");
+            then.status(200).json_body(serde_json::json!(builds));
+        });
+        let last_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/builds")
+                .query_param("project", "fetched")
+                .query_param("skip", "3");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let count = client
+            .builds_count(BuildsQuery::default().project("counted"))
+            .await
+            .unwrap();
+        assert_eq!(count, 1234);
+        counted.assert();
+
+        let count = client
+            .builds_count(BuildsQuery::default().project("fetched"))
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let count = client
+            .builds_count(BuildsQuery::default().project("fetched").max_results(1))
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+        count_page.assert_hits(2);
+        all_pages.assert_hits(2);
+        last_page.assert_hits(2);
+    }
+
+    #[tokio::test]
+    async fn it_get_job_by_name() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/job/linters");
+            then.status(200).json_body(serde_json::json!([
                 {
-                  "name": "Zuul Manifest",
-                  "url": "https://softwarefactory-project.io/logs/94/22894/1/gate/hlint/5bae560/zuul-manifest.json",
-                  "metadata": {
-                    "type": "zuul_manifest"
-                  }
+                    "name": "linters",
+                    "description": "Run the linters",
+                    "parent": "base",
+                    "branches": [],
+                    "nodeset": {"name": "fedora", "nodes": [], "groups": []}
                 },
                 {
-                  "name": "HLint report",
-                  "url": "https://softwarefactory-project.io/logs/94/22894/1/gate/hlint/5bae560/hlint.html"
+                    "name": "linters",
+                    "parent": "base",
+                    "branches": ["stable/.*"],
+                    "nodeset": "centos"
                 }
-              ],
-              "project": "software-factory/matrix-client-haskell",
-              "branch": "master",
-              "pipeline": "gate",
-              "change": 22894,
-              "patchset": "1",
-              "ref": "refs/changes/94/22894/1",
-              "ref_url": "https://softwarefactory-project.io/r/22894",
-              "event_id": "40d9b63d749c48eabb3d7918cfab0d31"
-            }"#;
-        let build: Build = serde_json::from_str(data).unwrap();
-        assert_eq!(build.uuid, "5bae5607ae964331bb5878aec0777637");
+            ]));
+        });
+        let empty = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/job/empty");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client
+            .tenant("local")
+            .get_job_by_name("linters")
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got.name, "linters");
+        assert_eq!(got.description.as_deref(), Some("Run the linters"));
+        assert_eq!(got.variants.len(), 2);
+        assert_eq!(got.variants[0].parent.as_deref(), Some("base"));
+        assert_eq!(got.variants[0].nodeset.as_deref(), Some("fedora"));
+        assert_eq!(got.variants[1].branches, ["stable/.*"]);
+        assert_eq!(got.variants[1].nodeset.as_deref(), Some("centos"));
+
+        assert!(matches!(
+            client.get_job_by_name("local", "empty").await,
+            Err(ZuulError::NotFound)
+        ));
+        empty.assert();
+    }
+
+    #[tokio::test]
+    async fn it_get_project_by_name() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/tenant/local/project/zuul%2Fzuul");
+            then.status(200).json_body(serde_json::json!({
+                "name": "zuul/zuul",
+                "canonical_name": "opendev.org/zuul/zuul",
+                "connection_name": "opendev",
+                "type": "untrusted",
+                "configs": [{
+                    "default_branch": "master",
+                    "merge_mode": "squash-merge",
+                    "templates": ["python-jobs"],
+                    "pipelines": [{
+                        "name": "check",
+                        "queue_name": null,
+                        "jobs": [[{"name": "linters"}]]
+                    }]
+                }]
+            }));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client
+            .tenant("local")
+            .get_project_by_name("zuul/zuul")
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got.type_, "untrusted");
+        assert_eq!(got.configs[0].default_branch.as_deref(), Some("master"));
+        assert_eq!(got.configs[0].templates, ["python-jobs"]);
+        assert_eq!(got.configs[0].pipelines[0].name, "check");
+        assert_eq!(got.configs[0].pipelines[0].jobs[0][0]["name"], "linters");
+    }
+
+    #[tokio::test]
+    async fn it_get_builds_grouped_by_buildset() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let with_buildset = |uuid: &str, buildset: Option<&str>| Build {
+            buildset_uuid: buildset.map(|uuid| uuid.to_string()),
+            ..make_build(uuid, now)
+        };
+        let builds = vec![
+            with_buildset("build1", Some("buildset1")),
+            with_buildset("build2", Some("buildset2")),
+            with_buildset("build3", Some("buildset1")),
+            with_buildset("build4", None),
+        ];
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "0");
+            then.status(200).json_body(serde_json::json!(builds));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/builds").query_param("skip", "4");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = create_client(&server.url("/")).unwrap();
+        let groups = client
+            .get_builds_grouped_by_buildset(&BuildsQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["buildset1"], [builds[0].clone(), builds[2].clone()]);
+        assert_eq!(groups["buildset2"], [builds[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn it_get_frozen_jobs() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path(
+                "/api/tenant/local/pipeline/check/project/zuul%2Fzuul/branch/stable%2F1.0/freeze-jobs",
+            );
+            then.status(200).json_body(serde_json::json!([
+                {"name": "build-image", "dependencies": []},
+                {"name": "deploy", "dependencies": [{"name": "build-image", "soft": false}]},
+                {"name": "linters", "dependencies": ["build-image"]}
+            ]));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let got = client
+            .tenant("local")
+            .get_frozen_jobs("check", "zuul/zuul", "stable/1.0")
+            .await
+            .unwrap();
+        m.assert();
+        assert_eq!(got.len(), 3);
+        assert!(got[0].dependencies.is_empty());
+        assert_eq!(got[1].dependencies, ["build-image"]);
+        assert_eq!(got[2].dependencies, ["build-image"]);
+    }
+
+    #[tokio::test]
+    async fn it_get_pipeline_status() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenant/local/status");
+            then.status(200).json_body(serde_json::json!({
+                "pipelines": [
+                    {"name": "check", "manager": "independent", "change_queues": []},
+                    {"name": "gate", "manager": "dependent", "change_queues": [{
+                        "name": "integrated",
+                        "heads": [[{
+                            "id": "42,1",
+                            "project": "zuul/zuul",
+                            "enqueue_time": 1620000000000u64,
+                            "jobs": [{"name": "tox-py38", "uuid": "1234", "result": "SUCCESS",
+                                      "voting": true, "elapsed_time": 1000,
+                                      "remaining_time": 0, "url": "stream/1234"}]
+                        }]]
+                    }]}
+                ]
+            }));
+        });
+
+        let client = create_client(&server.url("/api/")).unwrap();
+        let tenant = client.tenant("local");
+        let gate = tenant.get_pipeline_status("gate").await.unwrap();
+        assert_eq!(gate.manager, "dependent");
+        let item = &gate.change_queues[0].heads[0][0];
+        assert_eq!(item.jobs[0].result.as_deref(), Some("SUCCESS"));
+        assert!(matches!(
+            tenant.get_pipeline_status("post").await,
+            Err(ZuulError::NotFound)
+        ));
+        m.assert_hits(2);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn it_generates_valid_builds(build in Build::arbitrary()) {
+            proptest::prop_assert!(build.start_time < build.end_time);
+            proptest::prop_assert_eq!(
+                (build.end_time - build.start_time).num_seconds(),
+                build.duration_secs as i64
+            );
+            proptest::prop_assert!(build.uuid.chars().all(|c| c.is_ascii_hexdigit()));
+            let value = serde_json::to_value(&build).unwrap();
+            proptest::prop_assert_eq!(serde_json::from_value::<Build>(value).unwrap(), build);
+        }
+    }
+
+    #[test]
+    fn it_converts_value_to_build() {
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let value = serde_json::to_value(&build).unwrap();
+        assert_eq!(Build::try_from(value).unwrap(), build);
+        assert!(matches!(
+            Build::try_from(serde_json::json!({"uuid": "bad"})),
+            Err(ZuulError::Decode { uuid, .. }) if uuid == "bad"
+        ));
+    }
+
+    #[test]
+    fn it_converts_value_to_buildset() {
+        let build = make_build("build1", drop_milli(Utc::now()));
+        let value = serde_json::json!({
+            "uuid": "buildset1",
+            "result": "SUCCESS",
+            "project": "zuul/zuul",
+            "branch": "master",
+            "pipeline": "check",
+            "change": 42,
+            "patchset": "1",
+            "builds": [build.clone()]
+        });
+        let buildset = Buildset::try_from(value).unwrap();
+        assert_eq!(buildset.uuid, "buildset1");
+        assert_eq!(buildset.message, "");
+        assert_eq!(buildset.builds, [build]);
+        assert!(matches!(
+            Buildset::try_from(serde_json::json!({"uuid": "bad"})),
+            Err(ZuulError::Decode { uuid, .. }) if uuid == "bad"
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_rate_limits_requests() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_rate_limit(20.0)
+            .build()
+            .unwrap();
+        let start = std::time::Instant::now();
+        for _ in 0..3 {
+            // The clones share the limit
+            client.clone().get_tenants().await.unwrap();
+        }
+        assert!(start.elapsed() >= std::time::Duration::from_millis(90));
+        m.assert_hits(3);
+
+        for rate in [0.0, -1.0, f64::NAN] {
+            assert!(matches!(
+                ZuulClientBuilder::new("https://zuul")
+                    .with_rate_limit(rate)
+                    .build(),
+                Err(ZuulError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn it_caches_responses() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let tenants = server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([
+                {"name": "local", "projects": 1, "queue": 0}
+            ]));
+        });
+        let promote = server.mock(|when, then| {
+            when.method(POST).path("/api/tenant/local/promote");
+            then.status(200);
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_auth_token("secret")
+            .with_cache(std::time::Duration::from_secs(60))
+            .build()
+            .unwrap();
+        client.get_tenants().await.unwrap();
+        client.clone().get_tenants().await.unwrap();
+        tenants.assert_hits(1);
+
+        // Write operations clear the cache
+        let req = PromoteRequest {
+            tenant: "local".to_string(),
+            pipeline: "gate".to_string(),
+            changes: vec!["42,1".to_string()],
+        };
+        client.promote_change("local", &req).await.unwrap();
+        promote.assert();
+        client.get_tenants().await.unwrap();
+        tenants.assert_hits(2);
+
+        // Expired responses are queried again
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_cache(std::time::Duration::from_millis(0))
+            .build()
+            .unwrap();
+        client.get_tenants().await.unwrap();
+        client.get_tenants().await.unwrap();
+        tenants.assert_hits(4);
+    }
+
+    #[tokio::test]
+    async fn it_limits_connections() {
+        use httpmock::prelude::*;
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(serde_json::json!([]));
+        });
+
+        let client = ZuulClientBuilder::new(&server.url("/api/"))
+            .with_max_connections(1)
+            .build()
+            .unwrap();
+        let (first, second) = tokio::join!(client.get_tenants(), client.get_tenants());
+        first.unwrap();
+        second.unwrap();
+        m.assert_hits(2);
+    }
+
+    #[tokio::test]
+    async fn it_fetch_all_builds_concurrently() {
+        use httpmock::prelude::*;
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (0..5)
+            .map(|idx| make_build(&format!("build{}", idx), now))
+            .collect();
+        let server = MockServer::start();
+        let mock_page = |skip: usize, limit: usize| {
+            let page = builds[skip.min(builds.len())..(skip + limit).min(builds.len())].to_vec();
+            server.mock(move |when, then| {
+                when.method(GET)
+                    .path("/builds")
+                    .query_param("skip", skip.to_string())
+                    .query_param("limit", limit.to_string());
+                then.status(200).json_body(serde_json::json!(page));
+            })
+        };
+        let pages = [
+            mock_page(0, 2),
+            mock_page(2, 2),
+            mock_page(4, 2),
+            mock_page(5, 2),
+            mock_page(7, 2),
+            mock_page(9, 2),
+        ];
+        let capped = mock_page(2, 1);
+
+        let client = create_client(&server.url("/")).unwrap();
+        let got = client
+            .fetch_all_builds(BuildsQuery::default().limit(2).with_concurrency(3))
+            .await
+            .unwrap();
+        assert_eq!(got, builds);
+        pages.iter().for_each(|m| m.assert());
+
+        let got = client
+            .fetch_all_builds(
+                BuildsQuery::default()
+                    .limit(2)
+                    .max_results(3)
+                    .with_concurrency(3),
+            )
+            .await
+            .unwrap();
+        assert_eq!(got, builds[..3]);
+        pages[0].assert_hits(2);
+        capped.assert();
     }
 }
@@ -0,0 +1,246 @@
+//! A mocked api to test the code using the client, without a zuul.
+
+use crate::{create_client, Build, Job, Project, Tenant, Zuul, DEFAULT_PAGE_SIZE};
+use httpmock::Method::GET;
+use httpmock::{Mock, MockServer, Regex};
+use std::collections::HashMap;
+
+/// A zuul api served by an embedded http server.
+///
+/// The last added build is the first of the builds list, like the api returns the
+/// most recent build first. The builds query filters are not applied.
+pub struct MockZuul {
+    server: MockServer,
+    mocks: Vec<usize>,
+    builds: Vec<Build>,
+    tenants: Vec<Tenant>,
+    projects: HashMap<String, Vec<Project>>,
+    jobs: HashMap<String, Vec<Job>>,
+}
+
+impl MockZuul {
+    /// Start the http server.
+    pub fn start() -> Self {
+        let mut mock = MockZuul {
+            server: MockServer::start(),
+            mocks: Vec::new(),
+            builds: Vec::new(),
+            tenants: Vec::new(),
+            projects: HashMap::new(),
+            jobs: HashMap::new(),
+        };
+        mock.mount();
+        mock
+    }
+
+    /// The api url.
+    pub fn url(&self) -> String {
+        self.server.url("/api/")
+    }
+
+    /// Create a client using the api.
+    pub fn client(&self) -> Zuul {
+        create_client(&self.url()).expect("Invalid mock server url")
+    }
+
+    /// Add a build, served by the builds list of every tenant.
+    pub fn add_build(&mut self, build: Build) -> &mut Self {
+        self.builds.insert(0, build);
+        self.mount();
+        self
+    }
+
+    /// Add a tenant.
+    pub fn add_tenant(&mut self, tenant: Tenant) -> &mut Self {
+        self.tenants.push(tenant);
+        self.mount();
+        self
+    }
+
+    /// Add a project to a tenant.
+    pub fn add_project(&mut self, tenant: &str, project: Project) -> &mut Self {
+        self.projects
+            .entry(tenant.to_string())
+            .or_default()
+            .push(project);
+        self.mount();
+        self
+    }
+
+    /// Add a job to a tenant.
+    pub fn add_job(&mut self, tenant: &str, job: Job) -> &mut Self {
+        self.jobs.entry(tenant.to_string()).or_default().push(job);
+        self.mount();
+        self
+    }
+
+    /// Replace the server mocks with the current content.
+    fn mount(&mut self) {
+        for id in self.mocks.drain(..) {
+            Mock::new(id, &self.server).delete();
+        }
+        let server = &self.server;
+        let mut mocks = Vec::new();
+        let tenants = serde_json::json!(self.tenants);
+        mocks.push(server.mock(|when, then| {
+            when.method(GET).path("/api/tenants");
+            then.status(200).json_body(tenants);
+        }));
+        for (tenant, projects) in &self.projects {
+            let projects = serde_json::json!(projects);
+            mocks.push(server.mock(|when, then| {
+                when.method(GET)
+                    .path(format!("/api/tenant/{}/projects", tenant));
+                then.status(200).json_body(projects);
+            }));
+        }
+        for (tenant, jobs) in &self.jobs {
+            let jobs = serde_json::json!(jobs);
+            mocks.push(server.mock(|when, then| {
+                when.method(GET)
+                    .path(format!("/api/tenant/{}/jobs", tenant));
+                then.status(200).json_body(jobs);
+            }));
+        }
+        for build in &self.builds {
+            let path = format!("^/api/(tenant/[^/]+/)?build/{}$", build.uuid);
+            let build = serde_json::json!(build);
+            mocks.push(server.mock(|when, then| {
+                when.method(GET).path_matches(Regex::new(&path).unwrap());
+                then.status(200).json_body(build);
+            }));
+        }
+        let builds_path = Regex::new("^/api/(tenant/[^/]+/)?builds$").unwrap();
+        let total_count = self.builds.len().to_string();
+        // The mocks are matched in creation order, the shorter pages come first
+        for skip in 0..self.builds.len() {
+            let remaining = self.builds.len() - skip;
+            for limit in 1..remaining {
+                let page = serde_json::json!(self.builds[skip..skip + limit]);
+                mocks.push(server.mock(|when, then| {
+                    when.method(GET)
+                        .path_matches(builds_path.clone())
+                        .query_param("skip", skip.to_string())
+                        .query_param("limit", limit.to_string());
+                    then.status(200)
+                        .header("X-Total-Count", &total_count)
+                        .json_body(page);
+                }));
+            }
+            // A limit covering the remaining builds
+            let page = serde_json::json!(self.builds[skip..]);
+            mocks.push(server.mock(|when, then| {
+                when.method(GET)
+                    .path_matches(builds_path.clone())
+                    .query_param("skip", skip.to_string())
+                    .query_param_exists("limit");
+                then.status(200)
+                    .header("X-Total-Count", &total_count)
+                    .json_body(page);
+            }));
+            // Without a limit, the api returns a default page
+            let end = self.builds.len().min(skip + DEFAULT_PAGE_SIZE as usize);
+            let page = serde_json::json!(self.builds[skip..end]);
+            mocks.push(server.mock(|when, then| {
+                when.method(GET)
+                    .path_matches(builds_path.clone())
+                    .query_param("skip", skip.to_string());
+                then.status(200)
+                    .header("X-Total-Count", &total_count)
+                    .json_body(page);
+            }));
+        }
+        // The pages after the last build are empty
+        mocks.push(server.mock(|when, then| {
+            when.method(GET).path_matches(builds_path);
+            then.status(200)
+                .header("X-Total-Count", &total_count)
+                .json_body(serde_json::json!([]));
+        }));
+        self.mocks = mocks.into_iter().map(|mock| mock.id).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{drop_milli, make_build};
+    use crate::BuildsQuery;
+    use chrono::Utc;
+    use futures_util::stream::StreamExt;
+
+    #[tokio::test]
+    async fn it_mocks_the_api() {
+        let now = drop_milli(Utc::now());
+        let (b1, b2) = (make_build("build1", now), make_build("build2", now));
+        let mut zuul = MockZuul::start();
+        zuul.add_build(b1.clone())
+            .add_build(b2.clone())
+            .add_tenant(Tenant {
+                name: "local".to_string(),
+                projects: 1,
+                queue: 0,
+            })
+            .add_job(
+                "local",
+                Job {
+                    name: "linters".to_string(),
+                    description: None,
+                    tags: Vec::new(),
+                },
+            );
+
+        let client = zuul.client();
+        assert_eq!(client.get_tenants().await.unwrap()[0].name, "local");
+        assert_eq!(client.get_jobs("local").await.unwrap()[0].name, "linters");
+        assert_eq!(client.get_build("build1").await.unwrap(), b1);
+        let tenant = client.tenant("local");
+        assert_eq!(tenant.get_build("build2").await.unwrap(), b2);
+        assert_eq!(
+            tenant.builds_unsafe().await.unwrap(),
+            [b2.clone(), b1.clone()]
+        );
+        let builds: Vec<Build> = tenant.builds_stream_finite().collect().await;
+        assert_eq!(builds, [b2.clone(), b1.clone()]);
+        assert_eq!(
+            client.builds_count(&BuildsQuery::default()).await.unwrap(),
+            2
+        );
+
+        let b3 = make_build("build3", now);
+        zuul.add_build(b3.clone());
+        assert_eq!(client.builds_unsafe().await.unwrap(), [b3, b2, b1]);
+        assert!(client.get_projects("local").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_mocks_the_builds_pages() {
+        let now = drop_milli(Utc::now());
+        let builds: Vec<Build> = (1..=5)
+            .map(|idx| make_build(&format!("build{}", idx), now))
+            .collect();
+        let mut zuul = MockZuul::start();
+        for build in &builds {
+            zuul.add_build(build.clone());
+        }
+        let newest_first: Vec<Build> = builds.iter().rev().cloned().collect();
+
+        let client = zuul.client();
+        let latest = client.builds(0, 1).await.unwrap();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].as_ref().unwrap(), &newest_first[0]);
+        let page = client.builds(3, 10).await.unwrap();
+        assert_eq!(page.len(), 2);
+
+        let got = client
+            .fetch_all_builds(BuildsQuery::default().limit(2))
+            .await
+            .unwrap();
+        assert_eq!(got, newest_first);
+        let got: Vec<Build> = client
+            .builds_stream_filtered(BuildsQuery::default().limit(2))
+            .collect()
+            .await;
+        assert_eq!(got, newest_first);
+    }
+}